@@ -0,0 +1,653 @@
+// Runtime support linked into every RedditLang program
+#include <math.h>
+#include <setjmp.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+// Must match `ValueKind::tag`
+#define TAG_NULL 0
+#define TAG_NUMBER 1
+#define TAG_BOOLEAN 2
+#define TAG_STRING 3
+#define TAG_ARRAY 4
+#define TAG_FOOLEAN 5
+#define TAG_OBJECT 6
+#define TAG_MAP 7
+// Not a tag, `rl_check_type` gets it for arrays whose items can be anything
+#define TAG_ANY 255
+
+// Must match `any_type`
+typedef struct {
+    uint8_t tag;
+    int64_t data;
+} rl_any;
+
+// Must match `HEADER_FIELDS`, fields of the class come after it
+typedef struct rl_object {
+    int64_t refcount;
+    void (*drop)(struct rl_object *);
+    const char *class_name;
+} rl_object;
+
+// Must match `string_type`
+typedef struct {
+    rl_object header;
+    int64_t length;
+    // `length` bytes and a null terminator, for the standard library
+    char bytes[];
+} rl_string;
+
+typedef struct {
+    rl_object header;
+    int64_t length;
+    int64_t capacity;
+    rl_any *items;
+} rl_array;
+
+typedef struct {
+    rl_any key;
+    rl_any value;
+} rl_entry;
+
+typedef struct {
+    rl_object header;
+    int64_t length;
+    int64_t capacity;
+    // In the order they were added
+    rl_entry *entries;
+    // `capacity * 2` positions in `entries`, -1 when empty
+    int64_t *slots;
+} rl_map;
+
+void rl_retain_any(uint8_t tag, int64_t data);
+void rl_release_any(uint8_t tag, int64_t data);
+_Noreturn void rl_shoot(const rl_any *value);
+
+static void *allocate(size_t size) {
+    void *memory = malloc(size);
+    if (memory == NULL) {
+        fprintf(stderr, "Out of memory\n");
+        exit(1);
+    }
+    return memory;
+}
+
+// A random Foolean state: Nope, Yup, Dunno or Huh
+uint8_t rl_yeet(void) {
+    static int seeded = 0;
+    if (!seeded) {
+        srand((unsigned int)time(NULL));
+        seeded = 1;
+    }
+
+    return (uint8_t)(rand() % 4);
+}
+
+static void print_any(FILE *file, const rl_any *value) {
+    static const char *fooleans[] = {"Nope", "Yup", "Dunno", "Huh"};
+
+    switch (value->tag) {
+    case TAG_NULL:
+        fprintf(file, "wat");
+        break;
+    case TAG_NUMBER: {
+        double number;
+        memcpy(&number, &value->data, sizeof(number));
+        fprintf(file, "%g", number);
+        break;
+    }
+    case TAG_BOOLEAN:
+        fprintf(file, "%s", value->data ? "Yup" : "Nope");
+        break;
+    case TAG_STRING: {
+        const rl_string *string = (const rl_string *)(intptr_t)value->data;
+        fprintf(file, "\"%.*s\"", (int)string->length, string->bytes);
+        break;
+    }
+    case TAG_FOOLEAN:
+        fprintf(file, "%s", fooleans[value->data & 3]);
+        break;
+    case TAG_ARRAY: {
+        const rl_array *array = (const rl_array *)(intptr_t)value->data;
+        fprintf(file, "[");
+        for (int64_t i = 0; i < array->length; i++) {
+            fprintf(file, i == 0 ? "" : ", ");
+            print_any(file, &array->items[i]);
+        }
+        fprintf(file, "]");
+        break;
+    }
+    case TAG_MAP: {
+        const rl_map *map = (const rl_map *)(intptr_t)value->data;
+        fprintf(file, "{");
+        for (int64_t i = 0; i < map->length; i++) {
+            fprintf(file, i == 0 ? "" : ", ");
+            print_any(file, &map->entries[i].key);
+            fprintf(file, ": ");
+            print_any(file, &map->entries[i].value);
+        }
+        fprintf(file, "}");
+        break;
+    }
+    case TAG_OBJECT:
+        fprintf(file, "<%s>", ((rl_object *)(intptr_t)value->data)->class_name);
+        break;
+    default:
+        fprintf(file, "<value with tag %d>", value->tag);
+        break;
+    }
+}
+
+// Objects
+// Variables and fields holding an object count as a reference, it is destroyed when the count drops
+// to zero. Objects returned from calls have no references until they are stored
+
+static void destroy(rl_object *object) {
+    // `snoRt` gets the object as `me`, which must not destroy it again
+    object->refcount = 1;
+    object->drop(object);
+    free(object);
+}
+
+void rl_retain(rl_object *object) {
+    if (object != NULL) {
+        object->refcount++;
+    }
+}
+
+void rl_release(rl_object *object) {
+    if (object != NULL && --object->refcount <= 0) {
+        destroy(object);
+    }
+}
+
+void rl_unretain(rl_object *object) {
+    if (object != NULL) {
+        object->refcount--;
+    }
+}
+
+void rl_collect(rl_object *object) {
+    if (object != NULL && object->refcount <= 0) {
+        destroy(object);
+    }
+}
+
+// Strings, arrays and maps are objects too
+#define ANY_OBJECT(tag, data)                                                                      \
+    ((tag) == TAG_OBJECT || (tag) == TAG_STRING || (tag) == TAG_ARRAY || (tag) == TAG_MAP          \
+         ? (rl_object *)(intptr_t)(data)                                                           \
+         : NULL)
+
+void rl_retain_any(uint8_t tag, int64_t data) { rl_retain(ANY_OBJECT(tag, data)); }
+void rl_release_any(uint8_t tag, int64_t data) { rl_release(ANY_OBJECT(tag, data)); }
+void rl_unretain_any(uint8_t tag, int64_t data) { rl_unretain(ANY_OBJECT(tag, data)); }
+void rl_collect_any(uint8_t tag, int64_t data) { rl_collect(ANY_OBJECT(tag, data)); }
+
+// Strings
+// Never change after they are made. Literals are globals with a reference count so large that they
+// are never destroyed
+
+static void string_drop(rl_object *object) { (void)object; }
+
+// Like objects made by a call, nothing holds the new string yet
+static rl_string *string_allocate(int64_t length) {
+    rl_string *string = allocate(sizeof(rl_string) + length + 1);
+    string->header.refcount = 0;
+    string->header.drop = string_drop;
+    string->header.class_name = "String";
+    string->length = length;
+    string->bytes[length] = '\0';
+    return string;
+}
+
+// `left ⨋ right`, strings that nothing holds are done after being copied
+rl_string *rl_string_concat(rl_string *left, rl_string *right) {
+    rl_string *string = string_allocate(left->length + right->length);
+    memcpy(string->bytes, left->bytes, left->length);
+    memcpy(string->bytes + left->length, right->bytes, right->length);
+
+    rl_collect(&left->header);
+    if (right != left) {
+        rl_collect(&right->header);
+    }
+    return string;
+}
+
+static int string_equals(const rl_string *left, const rl_string *right) {
+    return left->length == right->length && memcmp(left->bytes, right->bytes, left->length) == 0;
+}
+
+// Arrays
+// Items are dynamically typed and held like variables. Indexes start at 1
+
+static void array_drop(rl_object *object) {
+    rl_array *array = (rl_array *)object;
+    for (int64_t i = array->length - 1; i >= 0; i--) {
+        rl_release_any(array->items[i].tag, array->items[i].data);
+    }
+    free(array->items);
+}
+
+// Like objects made by a call, nothing holds the new array yet
+rl_array *rl_array_new(int64_t capacity) {
+    rl_array *array = allocate(sizeof(rl_array));
+    array->header.refcount = 0;
+    array->header.drop = array_drop;
+    array->header.class_name = "Array";
+    array->length = 0;
+    array->capacity = capacity > 0 ? capacity : 4;
+    array->items = allocate(sizeof(rl_any) * array->capacity);
+    return array;
+}
+
+void rl_array_push(rl_array *array, const rl_any *value) {
+    if (array->length == array->capacity) {
+        array->capacity *= 2;
+        array->items = realloc(array->items, sizeof(rl_any) * array->capacity);
+        if (array->items == NULL) {
+            fprintf(stderr, "Out of memory\n");
+            exit(1);
+        }
+    }
+
+    rl_retain_any(value->tag, value->data);
+    array->items[array->length++] = *value;
+}
+
+// Maps
+// Keys are strings, keys and values are held like variables
+
+static void map_drop(rl_object *object) {
+    rl_map *map = (rl_map *)object;
+    for (int64_t i = map->length - 1; i >= 0; i--) {
+        rl_release_any(map->entries[i].value.tag, map->entries[i].value.data);
+        rl_release_any(map->entries[i].key.tag, map->entries[i].key.data);
+    }
+    free(map->entries);
+    free(map->slots);
+}
+
+// Like arrays, nothing holds the new map yet
+rl_map *rl_map_new(int64_t capacity) {
+    rl_map *map = allocate(sizeof(rl_map));
+    map->header.refcount = 0;
+    map->header.drop = map_drop;
+    map->header.class_name = "Map";
+    map->length = 0;
+    map->capacity = 4;
+    while (map->capacity < capacity) {
+        map->capacity *= 2;
+    }
+    map->entries = allocate(sizeof(rl_entry) * map->capacity);
+    map->slots = allocate(sizeof(int64_t) * map->capacity * 2);
+    memset(map->slots, -1, sizeof(int64_t) * map->capacity * 2);
+    return map;
+}
+
+static const rl_string *key_string(const rl_any *key) {
+    return (const rl_string *)(intptr_t)key->data;
+}
+
+static uint64_t hash(const rl_string *key) {
+    uint64_t hash = 14695981039346656037ull;
+    for (int64_t i = 0; i < key->length; i++) {
+        hash = (hash ^ (uint8_t)key->bytes[i]) * 1099511628211ull;
+    }
+    return hash;
+}
+
+// The slot that holds `key`, or the empty slot it would go in
+static int64_t *map_slot(const rl_map *map, const rl_string *key) {
+    uint64_t mask = (uint64_t)map->capacity * 2 - 1;
+    for (uint64_t i = hash(key) & mask;; i = (i + 1) & mask) {
+        int64_t *slot = &map->slots[i];
+        if (*slot == -1 || string_equals(key_string(&map->entries[*slot].key), key)) {
+            return slot;
+        }
+    }
+}
+
+static void map_grow(rl_map *map) {
+    map->capacity *= 2;
+    map->entries = realloc(map->entries, sizeof(rl_entry) * map->capacity);
+    if (map->entries == NULL) {
+        fprintf(stderr, "Out of memory\n");
+        exit(1);
+    }
+
+    free(map->slots);
+    map->slots = allocate(sizeof(int64_t) * map->capacity * 2);
+    memset(map->slots, -1, sizeof(int64_t) * map->capacity * 2);
+    for (int64_t i = 0; i < map->length; i++) {
+        *map_slot(map, key_string(&map->entries[i].key)) = i;
+    }
+}
+
+// Lengths of strings, arrays and maps, 0 for anything else
+int64_t rl_length_any(uint8_t tag, int64_t data) {
+    switch (tag) {
+    case TAG_STRING:
+        return ((rl_string *)(intptr_t)data)->length;
+    case TAG_ARRAY:
+        return ((rl_array *)(intptr_t)data)->length;
+    case TAG_MAP:
+        return ((rl_map *)(intptr_t)data)->length;
+    default:
+        return 0;
+    }
+}
+
+// `⅀` of dynamically typed values. Numbers and strings are equal when they hold the same value,
+// anything else only when it is the same
+uint8_t rl_equals(const rl_any *left, const rl_any *right) {
+    if (left->tag != right->tag) {
+        return 0;
+    }
+
+    switch (left->tag) {
+    case TAG_NUMBER: {
+        double l, r;
+        memcpy(&l, &left->data, sizeof(l));
+        memcpy(&r, &right->data, sizeof(r));
+        return l == r;
+    }
+    case TAG_STRING:
+        return string_equals((const rl_string *)(intptr_t)left->data,
+                          (const rl_string *)(intptr_t)right->data);
+    default:
+        return left->data == right->data;
+    }
+}
+
+_Noreturn static void shoot_message(const char *format, ...) {
+    va_list args;
+    va_start(args, format);
+    int length = vsnprintf(NULL, 0, format, args);
+    va_end(args);
+
+    rl_string *message = string_allocate(length);
+    va_start(args, format);
+    vsnprintf(message->bytes, length + 1, format, args);
+    va_end(args);
+
+    rl_any bullet = {TAG_STRING, (int64_t)(intptr_t)message};
+    rl_shoot(&bullet);
+}
+
+static const char *tag_name(uint8_t tag) {
+    static const char *names[] = {"wat",     "Number", "Boolean", "String",
+                                  "Array",   "Foolean", "object", "Map"};
+    return tag < sizeof(names) / sizeof(names[0]) ? names[tag] : "object";
+}
+
+// `<`, `>`, `<=` and `>=` of dynamically typed values, which are ordered like typed ones: numbers by
+// value, strings by their bytes and `Nope` before `Yup`. The result is compared with 0, a NaN is
+// unordered
+double rl_compare(const rl_any *left, const rl_any *right) {
+    if (left->tag != right->tag) {
+        shoot_message("Can not compare a %s with a %s", tag_name(left->tag),
+                      tag_name(right->tag));
+    }
+
+    switch (left->tag) {
+    case TAG_NUMBER: {
+        double l, r;
+        memcpy(&l, &left->data, sizeof(l));
+        memcpy(&r, &right->data, sizeof(r));
+        return l < r ? -1 : l > r ? 1 : l == r ? 0 : NAN;
+    }
+    case TAG_STRING: {
+        const rl_string *l = (const rl_string *)(intptr_t)left->data;
+        const rl_string *r = (const rl_string *)(intptr_t)right->data;
+        int order = memcmp(l->bytes, r->bytes, l->length < r->length ? l->length : r->length);
+        if (order != 0) {
+            return order;
+        }
+        // When one string starts with the other, the shorter one comes first
+        return (double)(l->length - r->length);
+    }
+    case TAG_BOOLEAN:
+        return (double)(left->data - right->data);
+    default:
+        shoot_message("%s values can not be ordered", tag_name(left->tag));
+    }
+}
+
+// The position of `index` in `container`. Appending is allowed right after the last item
+static int64_t array_position(const rl_any *container, const rl_any *index, int append) {
+    if (index->tag != TAG_NUMBER) {
+        shoot_message("An array index must be a Number, not a %s", tag_name(index->tag));
+    }
+
+    const rl_array *array = (const rl_array *)(intptr_t)container->data;
+    double number;
+    memcpy(&number, &index->data, sizeof(number));
+
+    int64_t limit = array->length + (append ? 1 : 0);
+    // The range is checked before the cast, which is undefined for NaN and numbers out of range
+    if (!(number >= 1 && number <= (double)limit) || number != (double)(int64_t)number) {
+        shoot_message("Index %g is out of bounds for an array of length %lld", number,
+                      (long long)array->length);
+    }
+    return (int64_t)number - 1;
+}
+
+static void check_key(const rl_any *key) {
+    if (key->tag != TAG_STRING) {
+        shoot_message("A map key must be a String, not a %s", tag_name(key->tag));
+    }
+}
+
+static void check_indexable(const rl_any *container) {
+    if (container->tag != TAG_ARRAY && container->tag != TAG_MAP) {
+        shoot_message("Can not index a %s", tag_name(container->tag));
+    }
+}
+
+// A missing key of a map is `wat`. A key that nothing holds is done afterwards
+void rl_index_get(const rl_any *container, const rl_any *index, rl_any *out) {
+    check_indexable(container);
+    if (container->tag == TAG_ARRAY) {
+        int64_t position = array_position(container, index, 0);
+        *out = ((rl_array *)(intptr_t)container->data)->items[position];
+        return;
+    }
+
+    check_key(index);
+    const rl_map *map = (const rl_map *)(intptr_t)container->data;
+    int64_t slot = *map_slot(map, key_string(index));
+    if (slot == -1) {
+        *out = (rl_any){TAG_NULL, 0};
+    } else {
+        *out = map->entries[slot].value;
+    }
+    rl_collect_any(index->tag, index->data);
+}
+
+// The old value is released after the new one is held, in case they are the same object
+static void replace(rl_any *slot, const rl_any *value) {
+    rl_any old = *slot;
+    rl_retain_any(value->tag, value->data);
+    *slot = *value;
+    rl_release_any(old.tag, old.data);
+}
+
+// Assigning right after the last item of an array appends it
+void rl_index_set(const rl_any *container, const rl_any *index, const rl_any *value) {
+    check_indexable(container);
+    if (container->tag == TAG_ARRAY) {
+        int64_t position = array_position(container, index, 1);
+        rl_array *array = (rl_array *)(intptr_t)container->data;
+        if (position == array->length) {
+            rl_array_push(array, value);
+        } else {
+            replace(&array->items[position], value);
+        }
+        return;
+    }
+
+    check_key(index);
+    rl_map *map = (rl_map *)(intptr_t)container->data;
+    int64_t *slot = map_slot(map, key_string(index));
+    if (*slot != -1) {
+        replace(&map->entries[*slot].value, value);
+        rl_collect_any(index->tag, index->data);
+        return;
+    }
+
+    if (map->length == map->capacity) {
+        map_grow(map);
+        slot = map_slot(map, key_string(index));
+    }
+
+    rl_entry *entry = &map->entries[map->length];
+    entry->key = (rl_any){TAG_NULL, 0};
+    entry->value = (rl_any){TAG_NULL, 0};
+    replace(&entry->key, index);
+    replace(&entry->value, value);
+    *slot = map->length++;
+}
+
+// `repeatdatshid x ∈ collection` goes through the items of an array, or the keys of a map
+
+int64_t rl_each_length(const rl_any *collection) {
+    if (collection->tag != TAG_ARRAY && collection->tag != TAG_MAP) {
+        shoot_message("Can not loop over a %s", tag_name(collection->tag));
+    }
+    return rl_length_any(collection->tag, collection->data);
+}
+
+void rl_each_get(const rl_any *collection, int64_t position, rl_any *out) {
+    if (collection->tag == TAG_ARRAY) {
+        *out = ((rl_array *)(intptr_t)collection->data)->items[position];
+    } else {
+        *out = ((rl_map *)(intptr_t)collection->data)->entries[position].key;
+    }
+}
+
+// Called before a member of an object variable is used, which could hold `wat`
+void rl_check_object(const rl_object *object, const char *member) {
+    if (object == NULL) {
+        shoot_message("Can not use `%s` on wat", member);
+    }
+}
+
+// Whether `value` is a `tag`. `drop` tells classes apart, objects can be `wat` and a Foolean can be
+// a Boolean
+static int has_type(const rl_any *value, uint8_t tag, void (*drop)(rl_object *)) {
+    if (value->tag == tag) {
+        return tag != TAG_OBJECT || ((rl_object *)(intptr_t)value->data)->drop == drop;
+    }
+    return (tag == TAG_OBJECT && value->tag == TAG_NULL) ||
+           (tag == TAG_FOOLEAN && value->tag == TAG_BOOLEAN);
+}
+
+// The class of an object, otherwise the name of its tag
+static const char *type_name(const rl_any *value) {
+    return value->tag == TAG_OBJECT ? ((rl_object *)(intptr_t)value->data)->class_name
+                                    : tag_name(value->tag);
+}
+
+// Called in debug builds before a dynamically typed value is used as a `damn` type. Every item of
+// an array is checked against `item_tag` and `item_drop`, unless it is `TAG_ANY`
+void rl_check_type(const rl_any *value, uint8_t tag, void (*drop)(rl_object *), uint8_t item_tag,
+                   void (*item_drop)(rl_object *), const char *expected) {
+    if (!has_type(value, tag, drop)) {
+        shoot_message("%s but got a %s", expected, type_name(value));
+    }
+    if (tag != TAG_ARRAY || item_tag == TAG_ANY) {
+        return;
+    }
+
+    const rl_array *array = (const rl_array *)(intptr_t)value->data;
+    for (int64_t i = 0; i < array->length; i++) {
+        if (!has_type(&array->items[i], item_tag, item_drop)) {
+            shoot_message("%s but item %lld is a %s", expected, (long long)(i + 1),
+                          type_name(&array->items[i]));
+        }
+    }
+}
+
+// Math other than `⨋` on a dynamically typed value, which has to be a Number
+double rl_to_number(const rl_any *value) {
+    if (value->tag != TAG_NUMBER) {
+        shoot_message("Can not do math with a %s", tag_name(value->tag));
+    }
+    double number;
+    memcpy(&number, &value->data, sizeof(number));
+    return number;
+}
+
+// `⨋` of dynamically typed values adds numbers and joins strings
+
+void rl_add(const rl_any *left, const rl_any *right, rl_any *out) {
+    if (left->tag == TAG_NUMBER && right->tag == TAG_NUMBER) {
+        double l, r;
+        memcpy(&l, &left->data, sizeof(l));
+        memcpy(&r, &right->data, sizeof(r));
+        double sum = l + r;
+        out->tag = TAG_NUMBER;
+        memcpy(&out->data, &sum, sizeof(sum));
+        return;
+    }
+
+    if (left->tag == TAG_STRING && right->tag == TAG_STRING) {
+        rl_string *string = rl_string_concat((rl_string *)(intptr_t)left->data,
+                                             (rl_string *)(intptr_t)right->data);
+        *out = (rl_any){TAG_STRING, (int64_t)(intptr_t)string};
+        return;
+    }
+
+    shoot_message("Can not add a %s and a %s", tag_name(left->tag), tag_name(right->tag));
+}
+
+// Bullets and walls
+// A `test` pushes a wall, `setjmp` is called on it by the compiled code so the jump lands in a live frame
+
+typedef struct rl_wall {
+    jmp_buf env;
+    struct rl_wall *previous;
+} rl_wall;
+
+static rl_wall *walls = NULL;
+static rl_any bullet;
+
+void *rl_wall_push(void) {
+    rl_wall *wall = allocate(sizeof(rl_wall));
+    wall->previous = walls;
+    walls = wall;
+    return &wall->env;
+}
+
+// Called when a `test` is left without a bullet, such as at its end or with `spez`
+void rl_wall_pop(void) {
+    rl_wall *wall = walls;
+    walls = wall->previous;
+    free(wall);
+}
+
+// Jumps over the releases at the end of every block between the `shoot` and the wall, the objects
+// their locals hold leak
+_Noreturn void rl_shoot(const rl_any *value) {
+    bullet = *value;
+
+    if (walls == NULL) {
+        fprintf(stderr, "Uncaught bullet: ");
+        print_any(stderr, &bullet);
+        fprintf(stderr, "\n");
+        exit(1);
+    }
+
+    jmp_buf env;
+    memcpy(env, walls->env, sizeof(jmp_buf));
+    rl_wall_pop();
+    longjmp(env, 1);
+}
+
+// The bullet a `wall` caught
+void rl_catch(rl_any *out) { *out = bullet; }
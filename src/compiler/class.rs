@@ -0,0 +1,405 @@
+use inkwell::{
+    types::{BasicTypeEnum, StructType},
+    values::{BasicValueEnum, PointerValue},
+    AddressSpace,
+};
+
+use crate::{
+    bug,
+    parser::{Class, ClassMod, Ident},
+    sema::types::Type,
+};
+
+use super::{
+    add_function,
+    compile_node::{compile_function, Compile, Compute},
+    get_function,
+    namespace::{define, resolve},
+    value::{check_kind, coerce, extern_function, Value, ValueKind},
+    CompileMetadata, Compiler, Symbol,
+};
+
+pub const CONSTRUCTOR: &str = "cooK";
+pub const DESTRUCTOR: &str = "snoRt";
+
+/// Every object starts with `{ reference count, drop function, class name }`, like `rl_object` in
+/// the runtime. Fields come after it
+const HEADER_FIELDS: u32 = 3;
+
+pub fn class_type<'a>(compiler: &Compiler<'a>, class: &str) -> StructType<'a> {
+    match compiler
+        .context
+        .get_struct_type(&format!("class.{}", class))
+    {
+        Some(x) => x,
+        None => bug!("CLASS_NOT_DECLARED({})", class),
+    }
+}
+
+pub fn class_name(class_type: StructType<'_>) -> String {
+    let name = class_type.get_name().unwrap().to_str().unwrap();
+    match name.strip_prefix("class.") {
+        Some(x) => x.to_string(),
+        None => bug!("NOT_A_CLASS({})", name),
+    }
+}
+
+/// Name of the LLVM function for `method`
+pub fn method_function_name(class: &str, method: &str) -> String {
+    format!("{}.{}", class, method)
+}
+
+/// Name of the function that runs `snoRt` and releases the fields, the runtime calls it before freeing
+pub fn drop_function_name(class: &str) -> String {
+    format!("{}.drop", class)
+}
+
+/// Adds the struct type and name of `class`, so others can refer to it before its fields are known
+pub fn declare_class_type(compiler: &Compiler<'_>, class: &Class) {
+    let public = class
+        .modifiers
+        .iter()
+        .any(|x| matches!(x, ClassMod::Public));
+    let ident = define(compiler, &class.ident.0, public);
+
+    compiler
+        .context
+        .opaque_struct_type(&format!("class.{}", ident));
+}
+
+/// Lays out the fields of `class` and declares its constructor, methods and drop function, as
+/// they were resolved when the program was checked
+pub fn declare_class<'a>(compiler: &Compiler<'a>, class: &Class) {
+    let ident = &resolve(compiler, &class.ident.0);
+    let info = match compiler.classes.get(ident) {
+        Some(x) => x,
+        None => bug!("CLASS_NOT_CHECKED({})", ident),
+    };
+
+    let i8_ptr_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let mut body: Vec<BasicTypeEnum<'a>> = vec![
+        compiler.context.i64_type().into(),
+        i8_ptr_type.into(),
+        i8_ptr_type.into(),
+    ];
+    body.extend(
+        info.fields
+            .iter()
+            .map(|x| ValueKind::from(&x.r#type).llvm_type(compiler)),
+    );
+    class_type(compiler, ident).set_body(&body, false);
+
+    // `call <CLASS>(...)` makes an object, taking the arguments of `cooK`
+    let names = class
+        .methods
+        .iter()
+        .map(|x| method_function_name(ident, &x.declaration.ident.0))
+        .chain([ident.to_string()]);
+    for name in names {
+        let signature = match compiler.functions.get(&name) {
+            Some(x) => x,
+            None => bug!("FUNCTION_NOT_CHECKED({})", name),
+        };
+        add_function(compiler, &name, signature.fn_type(compiler));
+    }
+
+    let drop_type = compiler
+        .context
+        .void_type()
+        .fn_type(&[i8_ptr_type.into()], false);
+    add_function(compiler, &drop_function_name(ident), drop_type);
+}
+
+impl<'a> Compile<'a> for Class {
+    fn compile(&self, compiler: &Compiler<'a>, _compile_meta: &mut CompileMetadata<'a>) {
+        let previous_block = compiler.builder.get_insert_block();
+        let ident = &resolve(compiler, &self.ident.0);
+
+        compile_constructor(compiler, self, ident);
+        compile_drop(compiler, self, ident);
+        for method in &self.methods {
+            let name = method_function_name(ident, &method.declaration.ident.0);
+            compile_function(compiler, &name, method, Some(ident));
+        }
+
+        if let Some(previous_block) = previous_block {
+            compiler.builder.position_at_end(previous_block);
+        }
+    }
+}
+
+fn has_method(class: &Class, method: &str) -> bool {
+    class
+        .methods
+        .iter()
+        .any(|x| x.declaration.ident.0 == method)
+}
+
+/// `ident` is the name `class` is compiled as
+fn compile_constructor(compiler: &Compiler<'_>, class: &Class, ident: &str) {
+    let function = get_function(compiler, ident);
+    let entry_block = compiler.context.append_basic_block(function, "entry");
+    compiler.builder.position_at_end(entry_block);
+
+    let class_type = class_type(compiler, ident);
+    let object = compiler.builder.build_malloc(class_type, "object").unwrap();
+
+    // The constructor holds the object until it returns, so `cooK` can not destroy it
+    let header: [BasicValueEnum; 3] = [
+        compiler.context.i64_type().const_int(1, false).into(),
+        compiler
+            .builder
+            .build_pointer_cast(
+                get_function(compiler, &drop_function_name(ident))
+                    .as_global_value()
+                    .as_pointer_value(),
+                compiler.context.i8_type().ptr_type(AddressSpace::default()),
+                "drop",
+            )
+            .into(),
+        compiler
+            .builder
+            .build_global_string_ptr(&class.ident.0, ".class")
+            .as_pointer_value()
+            .into(),
+    ];
+    for (i, value) in header.into_iter().enumerate() {
+        let ptr = compiler
+            .builder
+            .build_struct_gep(class_type, object, i as u32, "header")
+            .unwrap();
+        compiler.builder.build_store(ptr, value);
+    }
+
+    // Field initializers can use private members, but no variables
+    let compile_meta = CompileMetadata {
+        basic_block: entry_block,
+        function_scope: Default::default(),
+        walls: 0,
+        loops: vec![],
+    };
+    let fields = &compiler.classes[ident].fields;
+    for (i, (field, info)) in class.fields.iter().zip(fields).enumerate() {
+        let kind = ValueKind::from(&info.r#type);
+        let value = field.value.compute(compiler, &compile_meta);
+        check_kind(compiler, value, &info.r#type, || {
+            format!(
+                "Field `{}` of `{}` is declared as a {}",
+                field.declaration.ident.0, ident, info.r#type
+            )
+        });
+        let value = coerce(compiler, value, &kind);
+        retain(compiler, value);
+        let ptr = compiler
+            .builder
+            .build_struct_gep(class_type, object, HEADER_FIELDS + i as u32, "field")
+            .unwrap();
+        compiler.builder.build_store(ptr, value.into_basic_value());
+    }
+
+    if has_method(class, CONSTRUCTOR) {
+        let constructor = get_function(compiler, &method_function_name(ident, CONSTRUCTOR));
+        let args: Vec<_> = [object.into()]
+            .into_iter()
+            .chain(function.get_param_iter().map(|x| x.into()))
+            .collect();
+        compiler.builder.build_call(constructor, &args, "");
+    }
+
+    let object = Value::Object(object, class_type);
+    unretain(compiler, object);
+    compiler
+        .builder
+        .build_return(Some(&object.into_basic_value()));
+}
+
+fn compile_drop(compiler: &Compiler<'_>, class: &Class, ident: &str) {
+    let function = get_function(compiler, &drop_function_name(ident));
+    let entry_block = compiler.context.append_basic_block(function, "entry");
+    compiler.builder.position_at_end(entry_block);
+
+    let class_type = class_type(compiler, ident);
+    let object = compiler.builder.build_pointer_cast(
+        function.get_first_param().unwrap().into_pointer_value(),
+        class_type.ptr_type(AddressSpace::default()),
+        "object",
+    );
+
+    if has_method(class, DESTRUCTOR) {
+        let destructor = get_function(compiler, &method_function_name(ident, DESTRUCTOR));
+        compiler
+            .builder
+            .build_call(destructor, &[object.into()], "");
+    }
+
+    for (i, field) in compiler.classes[ident].fields.iter().enumerate().rev() {
+        let kind = ValueKind::from(&field.r#type);
+        let ptr = compiler
+            .builder
+            .build_struct_gep(class_type, object, HEADER_FIELDS + i as u32, "field")
+            .unwrap();
+        let value = compiler
+            .builder
+            .build_load(kind.llvm_type(compiler), ptr, "field");
+        release(compiler, Value::from_basic_value(compiler, value, &kind));
+    }
+
+    compiler.builder.build_return(None);
+}
+
+/// The pointer and class of an object that `member` is used on
+fn object_class<'a>(object: Value<'a>, member: &str) -> (PointerValue<'a>, String) {
+    match object {
+        Value::Object(ptr, class_type) => (ptr, class_name(class_type)),
+        x => bug!("MEMBER_OF({}, {})", x.kind(), member),
+    }
+}
+
+/// A pointer to the field `member` of `object`, with what the field holds. Its visibility was
+/// checked before the program was compiled
+pub fn member_pointer<'a>(
+    compiler: &Compiler<'a>,
+    object: Value<'a>,
+    member: &Ident,
+) -> (PointerValue<'a>, Type) {
+    let (ptr, class) = object_class(object, &member.0);
+    check_not_null(compiler, ptr, &member.0);
+    let (index, field) = match compiler.classes[&class]
+        .fields
+        .iter()
+        .enumerate()
+        .find(|(_, x)| x.ident == member.0)
+    {
+        Some(x) => x,
+        None => bug!("NO_FIELD({}, {})", class, member.0),
+    };
+
+    let field_ptr = compiler
+        .builder
+        .build_struct_gep(
+            class_type(compiler, &class),
+            ptr,
+            HEADER_FIELDS + index as u32,
+            &member.0,
+        )
+        .unwrap();
+    (field_ptr, field.r#type.clone())
+}
+
+/// Shoots a bullet when an object variable holds `wat` and `member` is used on it
+fn check_not_null<'a>(compiler: &Compiler<'a>, object: PointerValue<'a>, member: &str) {
+    let i8_ptr_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let check = extern_function(
+        compiler,
+        "rl_check_object",
+        compiler
+            .context
+            .void_type()
+            .fn_type(&[i8_ptr_type.into(), i8_ptr_type.into()], false),
+    );
+
+    let object = compiler
+        .builder
+        .build_pointer_cast(object, i8_ptr_type, "object");
+    let member = compiler
+        .builder
+        .build_global_string_ptr(member, ".member")
+        .as_pointer_value();
+    compiler
+        .builder
+        .build_call(check, &[object.into(), member.into()], "");
+}
+
+/// The function to call for `method` on `object`
+pub fn method_name<'a>(compiler: &Compiler<'a>, object: Value<'a>, method: &str) -> String {
+    let (ptr, class) = object_class(object, method);
+    check_not_null(compiler, ptr, method);
+    if !compiler.classes[&class].methods.contains_key(method) {
+        bug!("NO_METHOD({}, {})", class, method);
+    }
+    method_function_name(&class, method)
+}
+
+/// Objects, strings, arrays and maps are reference counted, variables and fields that hold one
+/// keep it alive
+fn reference_count<'a>(compiler: &Compiler<'a>, value: Value<'a>, name: &str) {
+    let void_type = compiler.context.void_type();
+    match value {
+        Value::Object(ptr, _) | Value::String(ptr) | Value::Array(ptr) | Value::Map(ptr) => {
+            let i8_ptr_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+            let function = extern_function(
+                compiler,
+                name,
+                void_type.fn_type(&[i8_ptr_type.into()], false),
+            );
+            let ptr = compiler
+                .builder
+                .build_pointer_cast(ptr, i8_ptr_type, "object");
+            compiler.builder.build_call(function, &[ptr.into()], "");
+        }
+        // Decided at runtime by the tag
+        Value::Any(x) => {
+            let tag = compiler
+                .builder
+                .build_extract_value(x, 0, "any_tag")
+                .unwrap();
+            let data = compiler
+                .builder
+                .build_extract_value(x, 1, "any_data")
+                .unwrap();
+            let function = extern_function(
+                compiler,
+                &format!("{}_any", name),
+                void_type.fn_type(&[tag.get_type().into(), data.get_type().into()], false),
+            );
+            compiler
+                .builder
+                .build_call(function, &[tag.into(), data.into()], "");
+        }
+        _ => {}
+    }
+}
+
+pub fn retain<'a>(compiler: &Compiler<'a>, value: Value<'a>) {
+    reference_count(compiler, value, "rl_retain");
+}
+
+/// Destroys the object when nothing holds it anymore
+pub fn release<'a>(compiler: &Compiler<'a>, value: Value<'a>) {
+    reference_count(compiler, value, "rl_release");
+}
+
+/// Gives up a reference without destroying the object, so it can be returned to a caller that
+/// will hold it
+pub fn unretain<'a>(compiler: &Compiler<'a>, value: Value<'a>) {
+    reference_count(compiler, value, "rl_unretain");
+}
+
+/// Destroys an object that nothing ever held, like the result of a call that was not used
+pub fn collect<'a>(compiler: &Compiler<'a>, value: Value<'a>) {
+    reference_count(compiler, value, "rl_collect");
+}
+
+/// Releases what `locals` hold, last declared first
+pub fn release_locals<'a>(compiler: &Compiler<'a>, locals: &[Symbol<'a>]) {
+    for symbol in locals.iter().rev() {
+        if !matches!(
+            symbol.kind,
+            ValueKind::Object(_)
+                | ValueKind::String
+                | ValueKind::Array
+                | ValueKind::Map
+                | ValueKind::Any
+        ) {
+            continue;
+        }
+
+        let value = compiler
+            .builder
+            .build_load(symbol.r#type, symbol.ptr, "local");
+        release(
+            compiler,
+            Value::from_basic_value(compiler, value, &symbol.kind),
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use inkwell::{
+    types::BasicTypeEnum,
+    values::{IntValue, PointerValue},
+    AddressSpace,
+};
+
+use crate::{bug, parser::Expr};
+
+use super::{
+    class::{collect, retain, unretain},
+    compile_node::Compute,
+    value::{any_type, box_value, extern_function, Value, ValueKind},
+    CompileMetadata, Compiler,
+};
+
+/// `[a, b, c]`, a new array holding the items as dynamically typed values
+pub fn array_literal<'a>(
+    compiler: &Compiler<'a>,
+    compile_meta: &CompileMetadata<'a>,
+    items: &[Expr],
+) -> Value<'a> {
+    let i8_ptr_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let i64_type = compiler.context.i64_type();
+    let any_ptr_type = any_type(compiler).ptr_type(AddressSpace::default());
+
+    let new = extern_function(
+        compiler,
+        "rl_array_new",
+        i8_ptr_type.fn_type(&[i64_type.into()], false),
+    );
+    let array = compiler
+        .builder
+        .build_call(
+            new,
+            &[i64_type.const_int(items.len() as u64, false).into()],
+            "array",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+
+    let push = extern_function(
+        compiler,
+        "rl_array_push",
+        compiler
+            .context
+            .void_type()
+            .fn_type(&[i8_ptr_type.into(), any_ptr_type.into()], false),
+    );
+    for item in items {
+        let value = item.compute(compiler, compile_meta);
+        let item = any_pointer(compiler, value, "item");
+        compiler
+            .builder
+            .build_call(push, &[array.into(), item.into()], "");
+    }
+
+    Value::Array(array)
+}
+
+/// `{"a": x, "b": y}`, a new map holding the values as dynamically typed values
+pub fn map_literal<'a>(
+    compiler: &Compiler<'a>,
+    compile_meta: &CompileMetadata<'a>,
+    entries: &[(Expr, Expr)],
+) -> Value<'a> {
+    let i8_ptr_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let i64_type = compiler.context.i64_type();
+
+    let new = extern_function(
+        compiler,
+        "rl_map_new",
+        i8_ptr_type.fn_type(&[i64_type.into()], false),
+    );
+    let map = compiler
+        .builder
+        .build_call(
+            new,
+            &[i64_type.const_int(entries.len() as u64, false).into()],
+            "map",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+
+    let map = Value::Map(map);
+    for (key, value) in entries {
+        let key = key.compute(compiler, compile_meta);
+        let value = value.compute(compiler, compile_meta);
+        index_set(compiler, map, key, value);
+    }
+    map
+}
+
+/// An alloca at the start of the function being compiled, so one inside of a loop does not grow the
+/// stack every time around
+pub fn entry_alloca<'a>(
+    compiler: &Compiler<'a>,
+    r#type: BasicTypeEnum<'a>,
+    name: &str,
+) -> PointerValue<'a> {
+    let function = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let entry_block = function.get_first_basic_block().unwrap();
+
+    let builder = compiler.context.create_builder();
+    match entry_block.get_first_instruction() {
+        Some(x) => builder.position_before(&x),
+        None => builder.position_at_end(entry_block),
+    }
+    builder.build_alloca(r#type, name)
+}
+
+/// Boxes `value` into memory, to pass it to the runtime
+pub fn any_pointer<'a>(compiler: &Compiler<'a>, value: Value<'a>, name: &str) -> PointerValue<'a> {
+    let ptr = entry_alloca(compiler, any_type(compiler).into(), name);
+    let any = box_value(compiler, value);
+    compiler.builder.build_store(ptr, any);
+    ptr
+}
+
+/// Indexes were checked before the program was compiled, the runtime checks dynamically typed ones
+fn check_index(container: &Value<'_>, index: &Value<'_>) {
+    match (container.kind(), index.kind()) {
+        (ValueKind::Array | ValueKind::Map | ValueKind::Any, ValueKind::Any)
+        | (ValueKind::Array, ValueKind::Number)
+        | (ValueKind::Map, ValueKind::String)
+        | (ValueKind::Any, ValueKind::Number | ValueKind::String) => {}
+        (container, index) => bug!("INDEX({} WITH {})", container, index),
+    }
+}
+
+/// `container[index]`, a bullet is shot when `index` is out of bounds. A missing key of a map is
+/// `wat`. A container or index that nothing holds is destroyed, the item outlives it
+pub fn index_get<'a>(compiler: &Compiler<'a>, container: Value<'a>, index: Value<'a>) -> Value<'a> {
+    check_index(&container, &index);
+    let (container_value, index_value) = (container, index);
+
+    let any_type = any_type(compiler);
+    let any_ptr_type = any_type.ptr_type(AddressSpace::default());
+    let get = extern_function(
+        compiler,
+        "rl_index_get",
+        compiler.context.void_type().fn_type(
+            &[
+                any_ptr_type.into(),
+                any_ptr_type.into(),
+                any_ptr_type.into(),
+            ],
+            false,
+        ),
+    );
+
+    let container = any_pointer(compiler, container, "container");
+    let index = any_pointer(compiler, index, "index");
+    let item = entry_alloca(compiler, any_type.into(), "item");
+    compiler
+        .builder
+        .build_call(get, &[container.into(), index.into(), item.into()], "");
+
+    let item = compiler.builder.build_load(any_type, item, "item");
+    let item = Value::Any(item.into_struct_value());
+
+    // Held while the container is collected, then given back like the result of a call
+    retain(compiler, item);
+    collect(compiler, container_value);
+    collect(compiler, index_value);
+    unretain(compiler, item);
+    item
+}
+
+/// `container[index] ∑ value`, an index right after the last item of an array appends `value`
+pub fn index_set<'a>(
+    compiler: &Compiler<'a>,
+    container: Value<'a>,
+    index: Value<'a>,
+    value: Value<'a>,
+) {
+    check_index(&container, &index);
+
+    let any_ptr_type = any_type(compiler).ptr_type(AddressSpace::default());
+    let set = extern_function(
+        compiler,
+        "rl_index_set",
+        compiler.context.void_type().fn_type(
+            &[
+                any_ptr_type.into(),
+                any_ptr_type.into(),
+                any_ptr_type.into(),
+            ],
+            false,
+        ),
+    );
+
+    let container = any_pointer(compiler, container, "container");
+    let index = any_pointer(compiler, index, "index");
+    let value = any_pointer(compiler, value, "value");
+    compiler
+        .builder
+        .build_call(set, &[container.into(), index.into(), value.into()], "");
+}
+
+/// How many times `repeatdatshid x ∈ collection` goes around, `collection` points to a dynamically
+/// typed value. A bullet is shot when it is not an array or a map
+pub fn each_length<'a>(compiler: &Compiler<'a>, collection: PointerValue<'a>) -> IntValue<'a> {
+    let i64_type = compiler.context.i64_type();
+    let length = extern_function(
+        compiler,
+        "rl_each_length",
+        i64_type.fn_type(&[collection.get_type().into()], false),
+    );
+
+    compiler
+        .builder
+        .build_call(length, &[collection.into()], "each_length")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value()
+}
+
+/// The item of an array or the key of a map at `position`, counting from 0
+pub fn each_get<'a>(
+    compiler: &Compiler<'a>,
+    collection: PointerValue<'a>,
+    position: IntValue<'a>,
+) -> Value<'a> {
+    let any_type = any_type(compiler);
+    let get = extern_function(
+        compiler,
+        "rl_each_get",
+        compiler.context.void_type().fn_type(
+            &[
+                collection.get_type().into(),
+                position.get_type().into(),
+                collection.get_type().into(),
+            ],
+            false,
+        ),
+    );
+
+    let item = entry_alloca(compiler, any_type.into(), "each_item");
+    compiler
+        .builder
+        .build_call(get, &[collection.into(), position.into(), item.into()], "");
+
+    let item = compiler.builder.build_load(any_type, item, "each_item");
+    Value::Any(item.into_struct_value())
+}
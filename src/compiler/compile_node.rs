@@ -1,56 +1,258 @@
 use inkwell::{
-    values::{
-        ArrayValue, BasicMetadataValueEnum, BasicValueEnum, FloatValue, IntValue, PointerValue,
-    },
-    FloatPredicate,
+    attributes::{Attribute, AttributeLoc},
+    basic_block::BasicBlock,
+    values::{BasicMetadataValueEnum, FunctionValue, PointerValue},
+    AddressSpace, IntPredicate,
 };
 
 use crate::{
-    bug, error,
-    parser::{Break, Call, Expr, IfBlock, Loop, MathOperator, Term, Variable},
+    bug,
+    parser::{
+        Assignment, Break, Call, Expr, Function, FunctionMod, Ident, IfBlock, IfNode, Loop,
+        MathOperator, Return, Term, Throw, Tree, TryCatch, UnaryOperator, Variable,
+    },
+    sema::types::Type,
 };
 
-use super::{compile, CompileMetadata, Compiler};
+use super::{
+    add_function, at,
+    class::{collect, member_pointer, method_name, release, release_locals, retain, unretain},
+    collection::{
+        any_pointer, array_literal, each_get, each_length, entry_alloca, index_get, index_set,
+        map_literal,
+    },
+    compile, function_name, get_function,
+    namespace::{define, resolve},
+    string::{string_bytes, string_literal},
+    value::{
+        add, any_type, box_value, check_kind, coerce, compare, extern_function, foolean,
+        to_boolean, to_number, Value, ValueKind,
+    },
+    CompileMetadata, Compiler, LoopContext, Symbol,
+};
 
+/// Lowers a statement. Programs are checked before they are compiled, so anything wrong here is a
+/// bug of the compiler
 pub trait Compile<'a> {
     fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>);
 }
 
 pub trait Compute<'a, T> {
-    fn compute(
-        &self,
-        compiler: &Compiler<'a>,
-        compile_meta: &CompileMetadata<'a>,
-    ) -> Result<T, Box<dyn std::error::Error>>;
+    fn compute(&self, compiler: &Compiler<'a>, compile_meta: &CompileMetadata<'a>) -> T;
 }
 
-impl<'a> Compile<'a> for Call {
-    fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
-        let function = match compiler.module.get_function(self.ident.0.as_str()) {
-            Some(x) => x,
-            None => error!("Function `{}` not defined", self.ident.0),
+impl<'a> Compute<'a, Value<'a>> for Call {
+    fn compute(&self, compiler: &Compiler<'a>, compile_meta: &CompileMetadata<'a>) -> Value<'a> {
+        // Methods are called with the object as the first argument
+        let (ident, receiver) = match &self.receiver {
+            Some(receiver) => {
+                let object = receiver.compute(compiler, compile_meta);
+                (method_name(compiler, object, &self.ident.0), Some(object))
+            }
+            None => (resolve(compiler, &self.ident.0), None),
         };
 
-        if function.is_null() || function.is_undef() {
-            error!("Function `{}` is null or undefined", self.ident.0);
+        let function = get_function(compiler, &ident);
+        let signature = &compiler.functions[&ident];
+
+        // Methods take the object before the arguments
+        let params = &signature.params[receiver.is_some() as usize..];
+        let mut values: Vec<Value<'a>> = receiver.into_iter().collect();
+        for (i, (arg, r#type)) in self.args.iter().zip(params).enumerate() {
+            let kind = ValueKind::from(r#type);
+            let value = arg.compute(compiler, compile_meta);
+            check_kind(compiler, value, r#type, || {
+                format!(
+                    "Argument {} of `{}` must be a {}",
+                    i + 1,
+                    self.ident.0,
+                    r#type
+                )
+            });
+            values.push(coerce(compiler, value, &kind));
         }
-
-        let args: Vec<BasicMetadataValueEnum<'_>> = self
-            .args
+        let args: Vec<BasicMetadataValueEnum<'_>> = values
             .iter()
-            .map(|x| x.compute(compiler, compile_meta).unwrap())
-            .map(|z| match z {
-                Value::Number(x) => x.into(),
-                Value::Boolean(x) => x.into(),
-                Value::String(x, _) => x.into(),
-                Value::Array(x) => x.into(),
-                Value::Null => todo!(),
+            .map(|x| match x {
+                Value::String(x) if signature.foreign => string_bytes(compiler, *x).into(),
+                x => x.into_basic_value().into(),
             })
             .collect();
 
-        compiler
+        let result = compiler
             .builder
-            .build_call(function, args.as_slice(), "call");
+            .build_call(function, args.as_slice(), "call")
+            .try_as_basic_value()
+            .left();
+
+        // Functions of the program hold their arguments, foreign ones do not
+        if signature.foreign {
+            for value in values {
+                collect(compiler, value);
+            }
+        }
+
+        match result {
+            Some(x) => Value::from_basic_value(compiler, x, &ValueKind::from(&signature.r#return)),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<'a> Compile<'a> for Call {
+    fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
+        // Nothing holds the result, so an object made by the call is done
+        let value = self.compute(compiler, compile_meta);
+        collect(compiler, value);
+    }
+}
+
+impl<'a> Compile<'a> for Return {
+    fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
+        let function = compile_meta.basic_block.get_parent().unwrap();
+        let ident = function_name(function).unwrap_or_default();
+        let value = self.value.compute(compiler, compile_meta);
+
+        pop_walls(compiler, compile_meta.walls);
+        match compiler.functions.get(&ident) {
+            Some(signature) => {
+                let kind = &ValueKind::from(&signature.r#return);
+
+                // The value outlives the locals, even if one of them is all that holds it
+                check_kind(compiler, value, &signature.r#return, || {
+                    format!("Function `{}` must `spez` a {}", ident, signature.r#return)
+                });
+                let value = coerce(compiler, value, kind);
+                retain(compiler, value);
+                release_locals(compiler, &compile_meta.function_scope.locals);
+                unretain(compiler, value);
+                compiler
+                    .builder
+                    .build_return(Some(&value.into_basic_value()));
+            }
+            // Top level, `spez` exits the program with a status code
+            None => {
+                check_kind(compiler, value, &Type::Number, || {
+                    "`spez` in `main.rl` must be a Number".to_string()
+                });
+                let status = match coerce(compiler, value, &ValueKind::Number) {
+                    Value::Number(x) => compiler.builder.build_float_to_signed_int(
+                        x,
+                        compiler.context.i32_type(),
+                        "status",
+                    ),
+                    _ => unreachable!(),
+                };
+                release_locals(compiler, &compile_meta.function_scope.locals);
+                compiler.builder.build_return(Some(&status));
+            }
+        }
+
+        // Anything after `spez` is dead code, but still needs a block to go into
+        let after_block = compiler.context.append_basic_block(function, "after_spez");
+        compiler.builder.position_at_end(after_block);
+    }
+}
+
+/// Adds `function` to the module without a body
+pub fn declare_function(compiler: &Compiler<'_>, function: &Function) {
+    let public = function
+        .modifiers
+        .iter()
+        .any(|x| matches!(x, FunctionMod::Public));
+    let ident = &define(compiler, &function.declaration.ident.0, public);
+    let signature = match compiler.functions.get(ident) {
+        Some(x) => x,
+        None => bug!("FUNCTION_NOT_CHECKED({})", ident),
+    };
+
+    add_function(compiler, ident, signature.fn_type(compiler));
+}
+
+/// Blocks with nothing branching to them, except the entry block
+fn is_unreachable(block: BasicBlock<'_>) -> bool {
+    block.get_first_use().is_none() && block.get_previous_basic_block().is_some()
+}
+
+impl<'a> Compile<'a> for Function {
+    fn compile(&self, compiler: &Compiler<'a>, _compile_meta: &mut CompileMetadata<'a>) {
+        let ident = resolve(compiler, &self.declaration.ident.0);
+        compile_function(compiler, &ident, self, None)
+    }
+}
+
+/// Compiles the body of `function`, which was declared as `ident`. Methods of `class` take the
+/// object as `me` before their other arguments
+pub fn compile_function<'a>(
+    compiler: &Compiler<'a>,
+    ident: &str,
+    function: &Function,
+    class: Option<&str>,
+) {
+    let function_value = get_function(compiler, ident);
+    let signature = &compiler.functions[ident];
+
+    let previous_block = compiler.builder.get_insert_block();
+    let entry_block = compiler.context.append_basic_block(function_value, "entry");
+    compiler.builder.position_at_end(entry_block);
+
+    // Functions only see their own arguments
+    let mut compile_meta = CompileMetadata {
+        basic_block: entry_block,
+        function_scope: Default::default(),
+        walls: 0,
+        loops: vec![],
+    };
+    let names = class
+        .map(|_| "me")
+        .into_iter()
+        .chain(function.args.iter().map(|x| x.ident.0.as_str()));
+    for ((param, ident), r#type) in function_value
+        .get_param_iter()
+        .zip(names)
+        .zip(&signature.params)
+    {
+        param.set_name(ident);
+        let kind = ValueKind::from(r#type);
+
+        let alloca = compiler.builder.build_alloca(param.get_type(), ident);
+        compiler.builder.build_store(alloca, param);
+        retain(compiler, Value::from_basic_value(compiler, param, &kind));
+        compile_meta.function_scope.declare(
+            ident,
+            Symbol {
+                ptr: alloca,
+                declared: r#type.clone(),
+                kind,
+                r#type: param.get_type(),
+            },
+        );
+    }
+
+    compile(compiler, &function.body, &mut compile_meta);
+
+    // The end of the body was reached without a `spez`
+    let last_block = compiler.builder.get_insert_block().unwrap();
+    if last_block.get_terminator().is_none() {
+        if is_unreachable(last_block) {
+            compiler.builder.build_unreachable();
+        } else {
+            release_locals(compiler, &compile_meta.function_scope.locals);
+            match ValueKind::from(&signature.r#return) {
+                ValueKind::Any => {
+                    let null = box_value(compiler, Value::Null);
+                    compiler.builder.build_return(Some(&null));
+                }
+                ValueKind::Null => {
+                    compiler.builder.build_return(None);
+                }
+                x => bug!("NO_SPEZ({}, {})", ident, x),
+            }
+        }
+    }
+
+    if let Some(previous_block) = previous_block {
+        compiler.builder.position_at_end(previous_block);
     }
 }
 
@@ -60,261 +262,581 @@ impl<'a> Compile<'a> for Loop {
         let loop_block = compiler.context.append_basic_block(function, "loop");
         let exit_block = compiler.context.append_basic_block(function, "exit");
 
+        // The collection of `x ∈ collection` is held until the loop ends, so the body can not
+        // destroy it
+        let locals = compile_meta.function_scope.locals.len();
+        let mut loop_meta = compile_meta.nested();
+        let each = match &self.each {
+            Some(each) => {
+                let collection = each.collection.compute(compiler, compile_meta);
+                if !matches!(
+                    collection.kind(),
+                    ValueKind::Array | ValueKind::Map | ValueKind::Any
+                ) {
+                    bug!("LOOP_OVER({})", collection.kind());
+                }
+
+                let collection_ptr = any_pointer(compiler, collection, "collection");
+                retain(compiler, collection);
+                loop_meta.function_scope.declare(
+                    "",
+                    Symbol {
+                        ptr: collection_ptr,
+                        declared: Type::Any,
+                        kind: ValueKind::Any,
+                        r#type: any_type(compiler).into(),
+                    },
+                );
+
+                let i64_type = compiler.context.i64_type();
+                let position = entry_alloca(compiler, i64_type.into(), "position");
+                compiler
+                    .builder
+                    .build_store(position, i64_type.const_zero());
+                Some((each, collection_ptr, position))
+            }
+            None => None,
+        };
+
         compiler.builder.build_unconditional_branch(loop_block);
 
         compiler.builder.position_at_end(loop_block);
-        compile(
-            &compiler,
-            &self.body,
-            &mut CompileMetadata {
-                basic_block: exit_block,
-                function_scope: compile_meta.function_scope.clone(),
-            },
-        );
-
-        // break YES
-        if loop_block.get_terminator().is_none() {
-            compiler.builder.build_unconditional_branch(loop_block);
+        let loop_locals = loop_meta.function_scope.locals.len();
+        let mut body_meta = loop_meta.nested();
+        body_meta.loops.push(LoopContext {
+            exit_block,
+            walls: compile_meta.walls,
+            locals: loop_locals,
+        });
+
+        // Goes to the next item before running the body, and stops after the last one
+        if let Some((each, collection, position)) = each {
+            let i64_type = compiler.context.i64_type();
+            let length = each_length(compiler, collection);
+            let current = compiler
+                .builder
+                .build_load(i64_type, position, "position")
+                .into_int_value();
+            let has_next =
+                compiler
+                    .builder
+                    .build_int_compare(IntPredicate::SLT, current, length, "has_next");
+            let each_block = compiler.context.append_basic_block(function, "each");
+            compiler
+                .builder
+                .build_conditional_branch(has_next, each_block, exit_block);
+
+            compiler.builder.position_at_end(each_block);
+            let item = each_get(compiler, collection, current);
+            let next = compiler.builder.build_int_add(
+                current,
+                i64_type.const_int(1, false),
+                "next_position",
+            );
+            compiler.builder.build_store(position, next);
+
+            let any_type = any_type(compiler);
+            let ptr = entry_alloca(compiler, any_type.into(), &each.ident.0);
+            compiler.builder.build_store(ptr, item.into_basic_value());
+            retain(compiler, item);
+            body_meta.function_scope.declare(
+                &each.ident.0,
+                Symbol {
+                    ptr,
+                    declared: Type::Any,
+                    kind: ValueKind::Any,
+                    r#type: any_type.into(),
+                },
+            );
         }
 
+        compile(compiler, &self.body, &mut body_meta);
+        close_block(compiler, &body_meta, loop_locals);
+
+        branch_if_open(compiler, loop_block);
+
         compiler.builder.position_at_end(exit_block);
+        close_block(compiler, &loop_meta, locals);
     }
 }
 
 impl<'a> Compile<'a> for Break {
-    fn compile(&self, compiler: &Compiler, compile_meta: &mut CompileMetadata<'_>) {
+    fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
+        let function = compile_meta.basic_block.get_parent().unwrap();
+        let r#loop = match compile_meta.loops.last() {
+            Some(x) => *x,
+            None => bug!("STHU_OUTSIDE_LOOP"),
+        };
+
+        pop_walls(compiler, compile_meta.walls - r#loop.walls);
+        release_locals(
+            compiler,
+            &compile_meta.function_scope.locals[r#loop.locals..],
+        );
         compiler
             .builder
-            .build_unconditional_branch(compile_meta.basic_block);
-        // compiler.builder.position_at_end(*return_block);
+            .build_unconditional_branch(r#loop.exit_block);
+
+        // Like `spez`, anything after `sthu` is dead code
+        let after_block = compiler.context.append_basic_block(function, "after_sthu");
+        compiler.builder.position_at_end(after_block);
     }
 }
 
-impl<'a> Compile<'a> for IfBlock {
-    fn compile(&self, _compiler: &Compiler<'a>, _compile_meta: &mut CompileMetadata<'_>) {
-        todo!();
-        // let function = basic_block.get_parent().unwrap();
-        // let after_block = compiler.context.append_basic_block(function, "if_after");
-        // let mut else_block = after_block;
-
-        // let cases = self
-        //     .if_nodes
-        //     .iter()
-        //     .map(|x| match x {
-        //         IfNode::Case(x) => {
-        //             let condition = to_boolean
-        // (&compiler, x.expr.compute(&compiler).unwrap());
-
-        //             compiler.builder.position_at_end(*basic_block);
-        //             let alloca = compiler
-        //                 .builder
-        //                 .build_alloca(condition.get_type(), "if_cond");
-        //             compiler.builder.build_store(alloca, condition);
-        //             let value =
-        //                 compiler
-        //                     .builder
-        //                     .build_ptr_to_int(alloca, condition.get_type(), "read");
-
-        //             let block = compiler.context.append_basic_block(function, "if");
-        //             compiler.builder.position_at_end(block);
-
-        //             compile(&compiler, &x.body, &block);
-
-        //             compiler.builder.position_at_end(block);
-        //             compiler.builder.build_unconditional_branch(after_block);
-
-        //             Some((value, block))
-        //         }
-        //         IfNode::Else(x) => {
-        //             let block = compiler.context.append_basic_block(function, "else");
-        //             else_block = block;
-        //             compiler.builder.position_at_end(block);
-
-        //             compile(&compiler, &x.body, &basic_block);
-
-        //             compiler.builder.position_at_end(block);
-        //             compiler.builder.build_unconditional_branch(after_block);
-
-        //             None
-        //         }
-        //     })
-        //     .filter(|x| x.is_some())
-        //     .map(|x| x.unwrap())
-        //     .collect::<Vec<_>>();
-
-        // // Will run the first block with a true value
-        // compiler.builder.position_at_end(*basic_block);
-        // compiler.builder.build_switch(
-        //     compiler.context.bool_type().const_all_ones(),
-        //     else_block,
-        //     &cases,
-        // );
-        // compiler.builder.position_at_end(after_block);
+/// Releases the variables declared after the first `from`, unless the end of the block was not
+/// reached, like after `sthu` or `spez`
+fn close_block<'a>(compiler: &Compiler<'a>, compile_meta: &CompileMetadata<'a>, from: usize) {
+    let block = compiler.builder.get_insert_block().unwrap();
+    if block.get_terminator().is_none() && !is_unreachable(block) {
+        release_locals(compiler, &compile_meta.function_scope.locals[from..]);
     }
 }
 
-fn to_boolean<'a>(compiler: &Compiler<'a>, value: Value<'a>) -> IntValue<'a> {
-    let float = |x: FloatValue<'a>| {
-        compiler.builder.build_float_compare(
-            FloatPredicate::ONE,
-            x,
-            x.get_type().const_zero(),
-            "expr_truthy",
-        )
-    };
-
-    let int = |x: IntValue<'a>| {
-        compiler.builder.build_int_compare(
-            inkwell::IntPredicate::NE,
-            x,
-            x.get_type().const_zero(),
-            "expr_truthy",
-        )
-    };
+/// Branches to `target` unless the current block already ended, such as with `sthu` or `spez`
+fn branch_if_open(compiler: &Compiler<'_>, target: BasicBlock<'_>) {
+    let block = compiler.builder.get_insert_block().unwrap();
+    if block.get_terminator().is_some() {
+        return;
+    }
 
-    match value {
-        Value::Number(x) => float(x),
-        Value::Boolean(x) => x, // Is already a 0 or a 1
-        Value::Null => compiler.context.bool_type().const_zero(),
-        Value::String(_ptr, len) => int(len),
-        Value::Array(_) => todo!(),
+    if is_unreachable(block) {
+        compiler.builder.build_unreachable();
+    } else {
+        compiler.builder.build_unconditional_branch(target);
     }
 }
 
-#[derive(Debug)]
-pub enum Value<'a> {
-    Number(FloatValue<'a>),
-    /// Bit-width of 1
-    Boolean(IntValue<'a>),
-    String(PointerValue<'a>, IntValue<'a>), //  ptr, length
-    Array(ArrayValue<'a>),
-    Null,
+impl<'a> Compile<'a> for IfBlock {
+    fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
+        let function = compile_meta.basic_block.get_parent().unwrap();
+        let after_block = compiler.context.append_basic_block(function, "if_after");
+
+        let compile_body = |body: &Tree| {
+            let mut body_meta = compile_meta.nested();
+            compile(compiler, body, &mut body_meta);
+            close_block(
+                compiler,
+                &body_meta,
+                compile_meta.function_scope.locals.len(),
+            );
+        };
+
+        for node in &self.if_nodes {
+            match node {
+                IfNode::Case(x) => {
+                    let value = x.expr.compute(compiler, compile_meta);
+                    let condition = to_boolean(compiler, value);
+
+                    let then_block = compiler.context.append_basic_block(function, "if");
+                    let else_block = compiler.context.append_basic_block(function, "if_else");
+                    compiler
+                        .builder
+                        .build_conditional_branch(condition, then_block, else_block);
+
+                    compiler.builder.position_at_end(then_block);
+                    compile_body(&x.body);
+                    branch_if_open(compiler, after_block);
+
+                    // The next case is tested in the else block
+                    compiler.builder.position_at_end(else_block);
+                }
+                IfNode::Else(x) => compile_body(&x.body),
+            }
+        }
+
+        // Falls through when no case matched and there is no `isn't`
+        branch_if_open(compiler, after_block);
+        compiler.builder.position_at_end(after_block);
+    }
 }
 
 impl<'a> Compute<'a, Value<'a>> for Expr {
-    fn compute(
-        &self,
-        compiler: &Compiler<'a>,
-        compile_meta: &CompileMetadata<'a>,
-    ) -> Result<Value<'a>, Box<dyn std::error::Error>> {
-        match self {
-            Expr::BinaryExpr(x) => {
-                let mut result = compiler.context.f64_type().const_zero();
-                for term in &x.terms {
-                    let operator = term.operator.as_ref().unwrap_or(&MathOperator::Add);
-                    let operand = compiler.context.f64_type().const_float(match term.operand {
-                        Term::Number(x) => x,
-                        _ => bug!("INVALID_OPERAND({:?})", term.operand),
-                    });
-
-                    result = match operator {
-                        MathOperator::Add => compiler
-                            .builder
-                            .build_float_add(result, operand, "expr_add"),
-                        MathOperator::Subtract => compiler
-                            .builder
-                            .build_float_sub(result, operand, "expr_sub"),
-                        MathOperator::Multiply => compiler
+    fn compute(&self, compiler: &Compiler<'a>, compile_meta: &CompileMetadata<'a>) -> Value<'a> {
+        at(compiler, self.span(), || match self {
+            Expr::UnaryExpr(x) => {
+                let operand = x.operand.compute(compiler, compile_meta);
+                match x.operator {
+                    UnaryOperator::Positive => Value::Number(to_number(compiler, operand)),
+                    UnaryOperator::Negative => Value::Number(
+                        compiler
                             .builder
-                            .build_float_mul(result, operand, "expr_mul"),
-                        MathOperator::Divide => compiler
+                            .build_float_neg(to_number(compiler, operand), "expr_neg"),
+                    ),
+                    UnaryOperator::Negation => Value::Boolean(
+                        compiler
                             .builder
-                            .build_float_div(result, operand, "expr_div"),
-                        MathOperator::XOR => {
-                            let result_flt =
-                                result.const_to_signed_int(compiler.context.i64_type());
-                            let operand_flt =
-                                operand.const_to_signed_int(compiler.context.i64_type());
-
-                            result_flt
-                                .const_xor(operand_flt)
-                                .const_signed_to_float(compiler.context.f64_type())
-                        }
-                    };
+                            .build_not(to_boolean(compiler, operand), "expr_not"),
+                    ),
+                }
+            }
+            Expr::BinaryExpr(x) => {
+                let left = x.left.compute(compiler, compile_meta);
+                let right = x.right.compute(compiler, compile_meta);
+                if let MathOperator::Add = x.operator {
+                    return add(compiler, left, right);
                 }
 
-                Ok(Value::Number(result))
+                let left = to_number(compiler, left);
+                let right = to_number(compiler, right);
+                let result = match x.operator {
+                    MathOperator::Add => unreachable!(),
+                    MathOperator::Subtract => {
+                        compiler.builder.build_float_sub(left, right, "expr_sub")
+                    }
+                    MathOperator::Multiply => {
+                        compiler.builder.build_float_mul(left, right, "expr_mul")
+                    }
+                    MathOperator::Divide => {
+                        compiler.builder.build_float_div(left, right, "expr_div")
+                    }
+                    MathOperator::XOR => {
+                        let i64_type = compiler.context.i64_type();
+                        let left = compiler.builder.build_float_to_signed_int(
+                            left,
+                            i64_type,
+                            "expr_xor_lhs",
+                        );
+                        let right = compiler.builder.build_float_to_signed_int(
+                            right,
+                            i64_type,
+                            "expr_xor_rhs",
+                        );
+
+                        let xor = compiler.builder.build_xor(left, right, "expr_xor");
+                        compiler.builder.build_signed_int_to_float(
+                            xor,
+                            compiler.context.f64_type(),
+                            "expr_xor",
+                        )
+                    }
+                };
+
+                Value::Number(result)
             }
-            Expr::ConditionalExpr(_) => todo!(),
-            Expr::IndexExpr(_) => todo!(),
-            Expr::Term(x) => Ok(x.compute(compiler, compile_meta)?),
-            Expr::Null => todo!(),
-        }
+            Expr::ConditionalExpr(x) => {
+                // `a < b < c` is `a < b` and `b < c`, every operand is computed once
+                let operands: Vec<_> = x
+                    .terms
+                    .iter()
+                    .map(|x| x.operand.compute(compiler, compile_meta))
+                    .collect();
+
+                let mut result = compiler.context.bool_type().const_all_ones();
+                for (i, term) in x.terms.iter().enumerate() {
+                    if let Some(operator) = &term.operator {
+                        let condition = compare(compiler, operands[i], operator, operands[i + 1]);
+                        result = compiler.builder.build_and(result, condition, "expr_and");
+                    }
+                }
+
+                Value::Boolean(result)
+            }
+            Expr::IndexExpr(x) => {
+                let mut value = x.term.compute(compiler, compile_meta);
+                for index in &x.indexes {
+                    let index = index.compute(compiler, compile_meta);
+                    value = index_get(compiler, value, index);
+                }
+                value
+            }
+            Expr::Term(x, _) => x.compute(compiler, compile_meta),
+            Expr::Null(_) => Value::Null,
+        })
     }
 }
 
 impl<'a> Compute<'a, Value<'a>> for Term {
-    fn compute(
-        &self,
-        compiler: &Compiler<'a>,
-        compile_meta: &CompileMetadata<'a>,
-    ) -> Result<Value<'a>, Box<dyn std::error::Error>> {
-        Ok(match self {
+    fn compute(&self, compiler: &Compiler<'a>, compile_meta: &CompileMetadata<'a>) -> Value<'a> {
+        match self {
             Term::Number(x) => Value::Number(compiler.context.f64_type().const_float(*x)),
-            Term::String(x) => Value::String(
-                compiler
-                    .builder
-                    .build_global_string_ptr(x, ".str")
-                    .as_pointer_value(),
-                compiler.context.i64_type().const_int(x.len() as u64, false),
-            ),
-            Term::Ident(x) => {
-                let ptr = compile_meta.function_scope.variables.get(&x.0).unwrap();
-
-                let loaded = compiler.builder.build_load(
-                    ptr.get_type(),
-                    *ptr,
-                    format!("{}_access", x.0).as_str(),
-                );
-
-                match loaded {
-                    BasicValueEnum::FloatValue(x) => Value::Number(x),
-                    BasicValueEnum::IntValue(x) => Value::Boolean(x),
-                    BasicValueEnum::PointerValue(x) => {
-                        Value::String(x, compiler.context.i64_type().const_int(3, false))
-                    }
-                    BasicValueEnum::ArrayValue(x) => Value::Array(x),
-                    _ => bug!("UNKNOWN_VAR_TYPE({:?})", loaded),
+            Term::String(x) => Value::String(string_literal(compiler, x)),
+            Term::Boolean(x) => {
+                Value::Boolean(compiler.context.bool_type().const_int(*x as u64, false))
+            }
+            Term::Foolean(x) => foolean(compiler, x),
+            Term::Member(x) => {
+                let mut object = load_variable(compiler, compile_meta, &x.object);
+                for member in &x.members {
+                    object = load_member(compiler, object, member);
                 }
+                object
             }
-        })
+            Term::Ident(x) => load_variable(compiler, compile_meta, x),
+            Term::Call(x) => x.compute(compiler, compile_meta),
+            Term::Array(x) => array_literal(compiler, compile_meta, x),
+            Term::Map(x) => map_literal(compiler, compile_meta, x),
+            Term::Expr(x) => x.compute(compiler, compile_meta),
+        }
     }
 }
 
 impl<'a> Compile<'a> for Variable {
     fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
-        let value = self.value.compute(compiler, compile_meta).unwrap();
-        match value {
-            Value::Number(x) => {
-                let alloca = compiler
-                    .builder
-                    .build_alloca(x.get_type(), self.declaration.ident.0.as_str());
-                compiler.builder.build_store(alloca, x);
+        let ident = &self.declaration.ident.0;
+        let key = (
+            compiler.namespace.borrow().module.clone(),
+            self.declaration.ident.1.start.offset,
+        );
+        let declared = match compiler.variables.get(&key) {
+            Some(x) => x.clone(),
+            None => bug!("VARIABLE_NOT_CHECKED({})", ident),
+        };
+        let kind = ValueKind::from(&declared);
+
+        let value = self.value.compute(compiler, compile_meta);
+        check_kind(compiler, value, &declared, || {
+            format!("Variable `{}` is declared as a {}", ident, declared)
+        });
+        let value = coerce(compiler, value, &kind);
+        retain(compiler, value);
+        let value = value.into_basic_value();
+        let r#type = value.get_type();
+
+        let alloca = entry_alloca(compiler, r#type, ident);
+        compiler.builder.build_store(alloca, value);
+
+        compile_meta.function_scope.declare(
+            ident,
+            Symbol {
+                ptr: alloca,
+                declared,
+                kind,
+                r#type,
+            },
+        ); // allows shadowing
+    }
+}
+
+impl<'a> Compile<'a> for Assignment {
+    fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
+        let ident = &self.ident.0;
+        let symbol = match compile_meta.function_scope.variables.get(ident) {
+            Some(x) => x.clone(),
+            None => bug!("VARIABLE_NOT_DEFINED({})", ident),
+        };
+
+        // `a.b[i] ∑ x` stores into the array in `a.b`, the items of a typed array keep its type
+        if let Some((last, path)) = self.indexes.split_last() {
+            let mut container = load_variable(compiler, compile_meta, &self.ident);
+            let (mut name, mut declared) = (ident, symbol.declared);
+            for member in &self.members {
+                let (ptr, field) = member_pointer(compiler, container, member);
+                container = load_pointer(compiler, ptr, &field, &member.0);
+                (name, declared) = (&member.0, field);
             }
-            Value::Boolean(x) => {
-                let alloca = compiler
-                    .builder
-                    .build_alloca(x.get_type(), self.declaration.ident.0.as_str());
-                compiler.builder.build_store(alloca, x);
+            for index in path {
+                let index = index.compute(compiler, compile_meta);
+                container = index_get(compiler, container, index);
+                declared = Type::Any;
             }
-            Value::String(x, _) => {
-                let ident = &self.declaration.ident.0;
-
-                let alloca = compiler.builder.build_alloca(x.get_type(), &ident);
-                compiler.builder.build_store(alloca, x);
 
-                compile_meta
-                    .function_scope
-                    .variables
-                    .insert(ident.to_string(), alloca); // allows shadowing
+            let index = last.compute(compiler, compile_meta);
+            let value = self.value.compute(compiler, compile_meta);
+            if let Type::Array(item) = &declared {
+                check_kind(compiler, value, item, || {
+                    format!("Items of `{}` are declared as a {}", name, item)
+                });
             }
-            Value::Array(x) => {
-                let alloca = compiler
-                    .builder
-                    .build_alloca(x.get_type(), self.declaration.ident.0.as_str());
-                compiler.builder.build_store(alloca, x);
+            index_set(compiler, container, index, value);
+            return;
+        }
+
+        // `a.b.c ∑ x` stores into the field `c` of `a.b`
+        let (name, ptr, declared) = match self.members.split_last() {
+            Some((field, path)) => {
+                let mut object = load_variable(compiler, compile_meta, &self.ident);
+                for member in path {
+                    object = load_member(compiler, object, member);
+                }
+                let (ptr, declared) = member_pointer(compiler, object, field);
+                (&field.0, ptr, declared)
             }
-            Value::Null => todo!(), // TODO: Nullptr
+            None => (ident, symbol.ptr, symbol.declared),
+        };
+        let kind = ValueKind::from(&declared);
+        let r#type = kind.llvm_type(compiler);
+
+        let value = self.value.compute(compiler, compile_meta);
+        check_kind(compiler, value, &declared, || {
+            format!("`{}` is declared as a {}", name, declared)
+        });
+        // The old value is released after the new one is held, in case they are the same object
+        let value = coerce(compiler, value, &kind);
+        retain(compiler, value);
+        let old = compiler.builder.build_load(r#type, ptr, "old");
+        compiler.builder.build_store(ptr, value.into_basic_value());
+        release(compiler, Value::from_basic_value(compiler, old, &kind));
+    }
+}
+
+fn load_variable<'a>(
+    compiler: &Compiler<'a>,
+    compile_meta: &CompileMetadata<'a>,
+    ident: &Ident,
+) -> Value<'a> {
+    let symbol = match compile_meta.function_scope.variables.get(&ident.0) {
+        Some(x) => x,
+        None => bug!("VARIABLE_NOT_DEFINED({})", ident.0),
+    };
+
+    let loaded = compiler.builder.build_load(
+        symbol.r#type,
+        symbol.ptr,
+        format!("{}_access", ident.0).as_str(),
+    );
+    Value::from_basic_value(compiler, loaded, &symbol.kind)
+}
+
+/// Loads the field `member` of `object`
+pub fn load_member<'a>(compiler: &Compiler<'a>, object: Value<'a>, member: &Ident) -> Value<'a> {
+    let (ptr, declared) = member_pointer(compiler, object, member);
+    load_pointer(compiler, ptr, &declared, &member.0)
+}
+
+/// Loads a field that holds a `declared`
+fn load_pointer<'a>(
+    compiler: &Compiler<'a>,
+    ptr: PointerValue<'a>,
+    declared: &Type,
+    name: &str,
+) -> Value<'a> {
+    let kind = ValueKind::from(declared);
+    let loaded = compiler.builder.build_load(
+        kind.llvm_type(compiler),
+        ptr,
+        format!("{}_access", name).as_str(),
+    );
+    Value::from_basic_value(compiler, loaded, &kind)
+}
+
+/// Removes the `count` innermost walls, for when `spez` or `sthu` jumps out of a `test`
+fn pop_walls(compiler: &Compiler<'_>, count: usize) {
+    let wall_pop_type = compiler.context.void_type().fn_type(&[], false);
+    let wall_pop = extern_function(compiler, "rl_wall_pop", wall_pop_type);
+    for _ in 0..count {
+        compiler.builder.build_call(wall_pop, &[], "");
+    }
+}
+
+/// `setjmp` returns a second time when a bullet is shot, LLVM has to know that
+fn setjmp_function<'a>(compiler: &Compiler<'a>) -> FunctionValue<'a> {
+    let env_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let setjmp_type = compiler
+        .context
+        .i32_type()
+        .fn_type(&[env_type.into()], false);
+    let setjmp = extern_function(compiler, "_setjmp", setjmp_type);
+
+    let returns_twice = compiler
+        .context
+        .create_enum_attribute(Attribute::get_named_enum_kind_id("returns_twice"), 0);
+    setjmp.add_attribute(AttributeLoc::Function, returns_twice);
+    setjmp
+}
+
+impl<'a> Compile<'a> for Throw {
+    fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
+        let function = compile_meta.basic_block.get_parent().unwrap();
+        let value = self.value.compute(compiler, compile_meta);
+        let bullet = box_value(compiler, value);
+
+        let any_type = any_type(compiler);
+        let alloca = entry_alloca(compiler, any_type.into(), "bullet");
+        compiler.builder.build_store(alloca, bullet);
+
+        let shoot_type = compiler
+            .context
+            .void_type()
+            .fn_type(&[any_type.ptr_type(AddressSpace::default()).into()], false);
+        let shoot = extern_function(compiler, "rl_shoot", shoot_type);
+        compiler.builder.build_call(shoot, &[alloca.into()], "");
+        compiler.builder.build_unreachable();
+
+        // Like `spez`, anything after `shoot` is dead code
+        let after_block = compiler.context.append_basic_block(function, "after_shoot");
+        compiler.builder.position_at_end(after_block);
+    }
+}
+
+impl<'a> Compile<'a> for TryCatch {
+    fn compile(&self, compiler: &Compiler<'a>, compile_meta: &mut CompileMetadata<'a>) {
+        let function = compile_meta.basic_block.get_parent().unwrap();
+        let any_type = any_type(compiler);
+
+        let env_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+        let wall_push = extern_function(compiler, "rl_wall_push", env_type.fn_type(&[], false));
+        let env = compiler
+            .builder
+            .build_call(wall_push, &[], "wall")
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+
+        // Zero the first time, then non-zero when a bullet hits the wall
+        let status = compiler
+            .builder
+            .build_call(setjmp_function(compiler), &[env.into()], "setjmp")
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        let caught = compiler.builder.build_int_compare(
+            IntPredicate::NE,
+            status,
+            status.get_type().const_zero(),
+            "caught",
+        );
+
+        let try_block = compiler.context.append_basic_block(function, "test");
+        let catch_block = compiler.context.append_basic_block(function, "wall");
+        let after_block = compiler.context.append_basic_block(function, "test_after");
+        compiler
+            .builder
+            .build_conditional_branch(caught, catch_block, try_block);
+
+        compiler.builder.position_at_end(try_block);
+        let locals = compile_meta.function_scope.locals.len();
+        let mut try_meta = CompileMetadata {
+            walls: compile_meta.walls + 1,
+            ..compile_meta.nested()
+        };
+        compile(compiler, &self.r#try.0, &mut try_meta);
+        close_block(compiler, &try_meta, locals);
+
+        let last_block = compiler.builder.get_insert_block().unwrap();
+        if last_block.get_terminator().is_none() && !is_unreachable(last_block) {
+            pop_walls(compiler, 1);
         }
+        branch_if_open(compiler, after_block);
+
+        // The runtime removed the wall before jumping here
+        compiler.builder.position_at_end(catch_block);
+        let mut catch_meta = compile_meta.nested();
+        if let Some(ident) = &self.catch.0 {
+            let alloca = entry_alloca(compiler, any_type.into(), &ident.0);
+            let catch_type = compiler
+                .context
+                .void_type()
+                .fn_type(&[any_type.ptr_type(AddressSpace::default()).into()], false);
+            let catch = extern_function(compiler, "rl_catch", catch_type);
+            compiler.builder.build_call(catch, &[alloca.into()], "");
+
+            let bullet = compiler.builder.build_load(any_type, alloca, "bullet");
+            retain(compiler, Value::Any(bullet.into_struct_value()));
+            catch_meta.function_scope.declare(
+                &ident.0,
+                Symbol {
+                    ptr: alloca,
+                    declared: Type::Any,
+                    kind: ValueKind::Any,
+                    r#type: any_type.into(),
+                },
+            );
+        }
+        compile(compiler, &self.catch.1, &mut catch_meta);
+        close_block(compiler, &catch_meta, locals);
+        branch_if_open(compiler, after_block);
+
+        compiler.builder.position_at_end(after_block);
     }
 }
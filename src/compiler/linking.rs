@@ -1,10 +1,18 @@
 use std::{fs, path::PathBuf, process::Command};
 
-use inkwell::{targets::TargetTriple, AddressSpace};
+use inkwell::targets::TargetTriple;
 
-use crate::{compiler::Compiler, git::clone_else_pull, project::Project};
+use crate::{
+    compiler::Compiler,
+    errors::Diagnostic,
+    git::clone_else_pull,
+    project::Project,
+    sema::types::{Signature, Type},
+};
 
 const STDLIB_URL: &str = "https://github.com/elijah629/redditlang-std";
+/// Support code the compiled program calls into, such as `Yeet`
+const RUNTIME_SOURCE: &str = include_str!("../../runtime/runtime.c");
 
 /// Builds libstd and returns a path to it
 pub fn build_libstd() -> Result<PathBuf, Box<dyn std::error::Error>> {
@@ -24,8 +32,8 @@ pub fn build_libstd() -> Result<PathBuf, Box<dyn std::error::Error>> {
 
     // TODO: Windows
     fs::rename(
-        &std_dir.join("target/release/libstd.a"),
-        &std_dir.join("libstd.a"),
+        std_dir.join("target/release/libstd.a"),
+        std_dir.join("libstd.a"),
     )?;
 
     Command::new("cargo")
@@ -44,43 +52,64 @@ pub fn link(
     std_path: &PathBuf,
     release: bool,
     no_std: bool,
-) -> PathBuf {
+) -> Result<PathBuf, Diagnostic> {
     let target_str = target_triple.as_str().to_str().unwrap();
 
     let compiler = cc::Build::new()
-        .target(&target_str)
-        .out_dir(&build_dir)
+        .target(target_str)
+        .out_dir(build_dir)
         .opt_level(if release { 3 } else { 0 })
-        .host(&target_str)
+        .host(target_str)
         .cargo_metadata(false)
         .get_compiler();
 
     let output_file = build_dir.join(&project.config.name);
 
+    let runtime_path = build_dir.join("runtime.c");
+    fs::write(&runtime_path, RUNTIME_SOURCE).unwrap();
+
     let mut command = compiler.to_command();
-    command.arg(&object_path);
+    command.arg(object_path);
+    command.arg(&runtime_path);
 
     if !no_std {
-        command.arg(&std_path);
+        command.arg(std_path);
     }
 
     command.arg("-o");
     command.arg(&output_file);
 
-    command.spawn().unwrap();
-    output_file
+    let status = command.spawn().unwrap().wait().unwrap();
+    if !status.success() {
+        return Err(
+            Diagnostic::error(format!("Linking failed with {}", status)).with_note(format!(
+                "The linker was `{}`, its output is above",
+                command.get_program().to_string_lossy()
+            )),
+        );
+    }
+    Ok(output_file)
+}
+
+/// The functions of libstd and their signatures
+pub fn libstd_functions() -> Vec<(&'static str, Signature)> {
+    vec![
+        // println
+        (
+            "coitusinterruptus",
+            Signature {
+                params: vec![Type::String],
+                r#return: Type::Null,
+                foreign: true,
+            },
+        ),
+    ]
 }
 
 pub fn define_libstd(compiler: &Compiler) {
-    let println_type = compiler.context.void_type().fn_type(
-        &[compiler
-            .context
-            .i8_type()
-            .ptr_type(AddressSpace::default())
-            .into()],
-        false,
-    );
-    compiler
-        .module
-        .add_function("coitusinterruptus", println_type, None);
+    for (name, signature) in libstd_functions() {
+        compiler
+            .module
+            .add_function(name, signature.fn_type(compiler), None);
+    }
 }
@@ -1,59 +1,311 @@
-use std::collections::HashMap;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
 
-use self::compile_node::Compile;
+use self::{
+    class::{declare_class, declare_class_type, release_locals},
+    compile_node::{declare_function, Compile},
+    linking::define_libstd,
+    namespace::{enter_module, Namespace},
+    value::ValueKind,
+};
 use crate::{
     bug,
-    parser::{Node, Tree},
+    errors::{bug_diagnostic, catch_bug, Diagnostic},
+    modules::{SourceFile, SourceModule},
+    parser::{Node, Span, Tree},
+    sema::{
+        types::{ClassInfo, Signature, Type},
+        Declarations,
+    },
 };
 use inkwell::{
-    basic_block::BasicBlock, builder::Builder, context::Context, module::Module,
-    values::PointerValue,
+    basic_block::BasicBlock,
+    builder::Builder,
+    context::Context,
+    module::Module,
+    types::{BasicTypeEnum, FunctionType},
+    values::{FunctionValue, PointerValue},
 };
 
+pub mod class;
+pub mod collection;
 pub mod compile_node;
 pub mod linking;
+pub mod namespace;
+pub mod string;
+pub mod value;
 
 pub struct Compiler<'ctx> {
     pub context: &'ctx Context,
     pub builder: Builder<'ctx>,
     pub module: Module<'ctx>,
+    /// Signatures of every function by the name it is compiled as, from
+    /// [`crate::sema::Declarations`]. The program was checked against them before it is compiled
+    pub functions: HashMap<String, Signature>,
+    /// Fields and methods of every class, by name
+    pub classes: HashMap<String, ClassInfo>,
+    /// What every `meth` declares its variable as, see [`crate::sema::Declarations::variables`]
+    pub variables: HashMap<(Option<String>, usize), Type>,
+    /// What the module being compiled can refer to
+    pub namespace: RefCell<Namespace>,
+    /// The file being compiled
+    pub file: RefCell<Rc<SourceFile>>,
+    /// The code in `file` being compiled, bugs of the compiler point at it
+    pub span: Cell<Span>,
+    /// Whether dynamically typed values are checked against their `damn` type when the program
+    /// runs, release builds leave it out
+    pub checks: bool,
+}
+
+/// Runs `f` with bugs pointing at `span`
+pub fn at<T>(compiler: &Compiler<'_>, span: Span, f: impl FnOnce() -> T) -> T {
+    let outer = compiler.span.replace(span);
+    let result = f();
+    compiler.span.set(outer);
+    result
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+pub struct Symbol<'a> {
+    pub ptr: PointerValue<'a>,
+    /// What the variable was declared as, later assignments must match it
+    pub declared: Type,
+    /// How `declared` is stored
+    pub kind: ValueKind,
+    /// The type behind `ptr`, used to load the variable
+    pub r#type: BasicTypeEnum<'a>,
+}
+
+#[derive(Clone, Default)]
 pub struct Scope<'a> {
-    pub variables: HashMap<String, PointerValue<'a>>,
+    pub variables: HashMap<String, Symbol<'a>>,
+    /// Every variable declared so far in order, including shadowed ones. They are released when
+    /// they go out of scope
+    pub locals: Vec<Symbol<'a>>,
+}
+
+impl<'a> Scope<'a> {
+    /// Adds a variable, shadowing any previous one with the same name
+    pub fn declare(&mut self, ident: &str, symbol: Symbol<'a>) {
+        self.locals.push(symbol.clone());
+        self.variables.insert(ident.to_string(), symbol);
+    }
 }
 
 pub struct CompileMetadata<'a> {
     pub basic_block: BasicBlock<'a>,
     pub function_scope: Scope<'a>,
+    /// How many `test` blocks in the current function are around the statement
+    pub walls: usize,
+    /// The loops in the current function around the statement, innermost last
+    pub loops: Vec<LoopContext<'a>>,
+}
+
+/// Where `sthu` goes to leave a loop
+#[derive(Clone, Copy, Debug)]
+pub struct LoopContext<'a> {
+    /// The block after the loop
+    pub exit_block: BasicBlock<'a>,
+    /// What `walls` was at the start of the loop
+    pub walls: usize,
+    /// How many locals were declared at the start of the loop
+    pub locals: usize,
+}
+
+impl<'a> CompileMetadata<'a> {
+    /// Metadata for a block inside the current one, variables it declares are not seen outside of it
+    pub fn nested(&self) -> Self {
+        Self {
+            basic_block: self.basic_block,
+            function_scope: self.function_scope.clone(),
+            walls: self.walls,
+            loops: self.loops.clone(),
+        }
+    }
 }
 
 pub fn compile<'a>(compiler: &Compiler<'a>, tree: &Tree, compile_meta: &mut CompileMetadata<'a>) {
+    // Classes and functions can be used before they are defined. Every class name is known before
+    // any fields or signatures are resolved, so they can refer to each other
+    for node in tree {
+        if let Node::Class(class) = node {
+            at(compiler, class.span, || declare_class_type(compiler, class));
+        }
+    }
+    for node in tree {
+        if let Node::Class(class) = node {
+            at(compiler, class.span, || declare_class(compiler, class));
+        }
+    }
+    for node in tree {
+        if let Node::Function(function) = node {
+            at(compiler, function.span, || {
+                declare_function(compiler, function)
+            });
+        }
+    }
+
     for node in tree {
-        compile_one(&compiler, &node, compile_meta);
+        compile_one(compiler, node, compile_meta);
+    }
+}
+
+/// Prefixes the functions of the program in the object file, so they can not clash with `main`,
+/// libc or the runtime
+const SYMBOL_PREFIX: &str = "rl.";
+
+/// The symbol of the function that is compiled as `name`. Foreign functions keep their name
+fn symbol(compiler: &Compiler<'_>, name: &str) -> String {
+    match compiler.functions.get(name) {
+        Some(x) if x.foreign => name.to_string(),
+        _ => format!("{}{}", SYMBOL_PREFIX, name),
     }
 }
 
+/// Adds the function of the program that is compiled as `name`, without a body
+pub fn add_function<'a>(
+    compiler: &Compiler<'a>,
+    name: &str,
+    fn_type: FunctionType<'a>,
+) -> FunctionValue<'a> {
+    compiler
+        .module
+        .add_function(&symbol(compiler, name), fn_type, None)
+}
+
+/// The function that was added as `name`
+pub fn get_function<'a>(compiler: &Compiler<'a>, name: &str) -> FunctionValue<'a> {
+    match compiler.module.get_function(&symbol(compiler, name)) {
+        Some(x) => x,
+        None => bug!("FUNCTION_NOT_DECLARED({})", name),
+    }
+}
+
+/// The name `function` is compiled as, `None` for `main`
+pub fn function_name(function: FunctionValue<'_>) -> Option<String> {
+    let symbol = function.get_name().to_str().unwrap();
+    symbol.strip_prefix(SYMBOL_PREFIX).map(str::to_string)
+}
+
+/// Compiles a checked program into an LLVM module, whose `main` runs the imported modules and
+/// then `main.rl`. `modules` are ordered like [`crate::sema::check`] takes them. Bugs of the
+/// compiler are returned instead of crashing, pointing at the code they were hit on
+pub fn compile_program<'ctx>(
+    context: &'ctx Context,
+    modules: &[SourceModule],
+    declarations: Declarations,
+    checks: bool,
+) -> Result<Module<'ctx>, Diagnostic> {
+    let compiler = Compiler {
+        context,
+        module: context.create_module("main"),
+        builder: context.create_builder(),
+        functions: declarations.functions,
+        classes: declarations.classes,
+        variables: declarations.variables,
+        namespace: RefCell::new(Namespace::default()),
+        file: RefCell::default(),
+        span: Cell::default(),
+        checks,
+    };
+
+    let compiled = catch_bug(|| {
+        let (main_module, modules) = match modules.split_last() {
+            Some(x) => x,
+            None => bug!("NO_MAIN_MODULE"),
+        };
+        define_libstd(&compiler);
+        let module_inits: Vec<_> = modules
+            .iter()
+            .map(|x| compile_module(&compiler, x))
+            .collect();
+
+        let main_type = context.i32_type().fn_type(&[], false);
+        let main_fn = compiler.module.add_function("main", main_type, None);
+        let entry_block = context.append_basic_block(main_fn, "");
+        compiler.builder.position_at_end(entry_block);
+
+        // Imported modules run first, each after the modules it imports
+        for init in module_inits {
+            compiler.builder.build_call(init, &[], "");
+        }
+
+        let mut compile_meta = CompileMetadata {
+            basic_block: entry_block,
+            function_scope: Scope::default(),
+            walls: 0,
+            loops: vec![],
+        };
+        compiler.file.replace(main_module.file.clone());
+        enter_module(&compiler, None, &main_module.imports);
+        compile(&compiler, &main_module.tree, &mut compile_meta);
+
+        // Objects still held at the top level are destroyed before returning
+        release_locals(&compiler, &compile_meta.function_scope.locals);
+        compiler
+            .builder
+            .build_return(Some(&context.i32_type().const_zero()));
+    });
+    if let Err(x) = compiled {
+        let span = compiler.span.get();
+        return Err(x.with_span(span).in_file(&compiler.file.borrow()));
+    }
+
+    // The program was checked, so anything LLVM finds wrong is a bug of the compiler
+    if let Err(x) = compiler.module.verify() {
+        let message = x.to_string();
+        return Err(bug_diagnostic("MODULE_VERIFICATION_FAILED").with_note(message.trim_end()));
+    }
+    Ok(compiler.module)
+}
+
+/// Compiles a module imported by another file. Its top level code goes in a function that `main`
+/// calls before running `main.rl`
+pub fn compile_module<'a>(compiler: &Compiler<'a>, source: &SourceModule) -> FunctionValue<'a> {
+    let module = source.name.as_deref().unwrap();
+    compiler.file.replace(source.file.clone());
+    enter_module(compiler, Some(module), &source.imports);
+
+    let init_type = compiler.context.void_type().fn_type(&[], false);
+    let init = add_function(compiler, &format!("{}.init", module), init_type);
+    let entry_block = compiler.context.append_basic_block(init, "entry");
+    compiler.builder.position_at_end(entry_block);
+
+    let mut compile_meta = CompileMetadata {
+        basic_block: entry_block,
+        function_scope: Scope::default(),
+        walls: 0,
+        loops: vec![],
+    };
+    compile(compiler, &source.tree, &mut compile_meta);
+
+    release_locals(compiler, &compile_meta.function_scope.locals);
+    compiler.builder.build_return(None);
+    init
+}
+
 pub fn compile_one<'a>(
     compiler: &Compiler<'a>,
     node: &Node,
     compile_meta: &mut CompileMetadata<'a>,
 ) {
-    match node {
+    at(compiler, node.span(), || match node {
         Node::Loop(r#loop) => r#loop.compile(compiler, compile_meta),
-        Node::Break(r#break) => r#break.compile(compiler, compile_meta), // Need to fix,                                                   but won't                                          it's hard
-        Node::Function(_) => todo!(),
+        Node::Break(r#break) => r#break.compile(compiler, compile_meta),
+        Node::Function(function) => function.compile(compiler, compile_meta),
         Node::Call(call) => call.compile(compiler, compile_meta),
-        Node::Throw(_) => todo!(),
-        Node::Import(_) => todo!(),
-        Node::Module(_) => todo!(),
-        Node::TryCatch(_) => todo!(),
+        Node::Throw(throw) => throw.compile(compiler, compile_meta),
+        // Modules are loaded and compiled before the file that imports them
+        Node::Import(_) | Node::Module(_) => {}
+        Node::TryCatch(try_catch) => try_catch.compile(compiler, compile_meta),
         Node::Variable(var) => var.compile(compiler, compile_meta),
-        Node::Assignment(_) => todo!(),
+        Node::Assignment(assignment) => assignment.compile(compiler, compile_meta),
         Node::If(r#if) => r#if.compile(compiler, compile_meta),
-        Node::Class(_) => todo!(),
-        Node::Return(_) => todo!(),
+        Node::Class(class) => class.compile(compiler, compile_meta),
+        Node::Return(r#return) => r#return.compile(compiler, compile_meta),
         Node::Expr(_) => bug!("EXPR_IS_STATEMENT_COMPILER"),
-    }
+    })
 }
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::bug;
+
+use super::Compiler;
+
+/// The functions and classes the module being compiled can use
+#[derive(Default)]
+pub struct Namespace {
+    /// `None` for `main.rl`, whose items keep their names
+    pub module: Option<String>,
+    /// Names that can be used, and the names they are compiled as
+    pub names: HashMap<String, String>,
+    /// The `bar` items of every module compiled so far, by module
+    pub exports: HashMap<String, HashMap<String, String>>,
+}
+
+impl Namespace {
+    /// Starts `module`, which can use the `bar` items of `imports`. They must all have been entered
+    /// already. The namespace is usable even when two imports clash
+    pub fn enter(&mut self, module: Option<&str>, imports: &[String]) -> Result<(), String> {
+        self.module = module.map(str::to_string);
+        if let Some(module) = module {
+            self.exports.insert(module.to_string(), HashMap::new());
+        }
+
+        let mut names = HashMap::new();
+        let mut clash = None;
+        for import in imports {
+            for (ident, name) in &self.exports[import] {
+                if names.insert(ident.to_string(), name.to_string()).is_some() && clash.is_none() {
+                    clash = Some(format!(
+                        "`{}` is defined by more than one module imported by {}",
+                        ident,
+                        module_description(module)
+                    ));
+                }
+            }
+        }
+        self.names = names;
+
+        match clash {
+            Some(x) => Err(x),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds a function or class to the module, returning the name it is compiled as. Items of
+    /// other modules are prefixed with their module, so they can not clash
+    pub fn define(&mut self, ident: &str, public: bool) -> Result<String, String> {
+        if self.names.contains_key(ident) {
+            return Err(format!("`{}` is already defined", ident));
+        }
+
+        let name = match &self.module {
+            Some(module) => format!("{}::{}", module, ident),
+            None => ident.to_string(),
+        };
+        self.names.insert(ident.to_string(), name.to_string());
+
+        if public {
+            if let Some(module) = self.module.clone() {
+                self.exports
+                    .entry(module)
+                    .or_default()
+                    .insert(ident.to_string(), name.to_string());
+            }
+        }
+        Ok(name)
+    }
+
+    /// The name `ident` is compiled as. Anything the module did not define or import, like the
+    /// standard library, keeps its name
+    pub fn resolve(&self, ident: &str) -> String {
+        match self.names.get(ident) {
+            Some(x) => x.to_string(),
+            None => ident.to_string(),
+        }
+    }
+}
+
+fn module_description(module: Option<&str>) -> String {
+    match module {
+        Some(x) => format!("`{}`", x),
+        None => "`main.rl`".to_string(),
+    }
+}
+
+/// Starts compiling `module`, which can use the `bar` items of `imports`. They must all have been
+/// compiled already
+pub fn enter_module(compiler: &Compiler<'_>, module: Option<&str>, imports: &[String]) {
+    // Imports that clash were reported when the program was checked
+    if let Err(x) = compiler.namespace.borrow_mut().enter(module, imports) {
+        bug!("IMPORTS_CLASH({})", x);
+    }
+}
+
+/// Adds a function or class to the module being compiled, returning the name it is compiled as
+pub fn define(compiler: &Compiler<'_>, ident: &str, public: bool) -> String {
+    let result = compiler.namespace.borrow_mut().define(ident, public);
+    match result {
+        Ok(x) => x,
+        Err(x) => bug!("ALREADY_DEFINED({})", x),
+    }
+}
+
+/// The name `ident` is compiled as in the module being compiled
+pub fn resolve(compiler: &Compiler<'_>, ident: &str) -> String {
+    compiler.namespace.borrow().resolve(ident)
+}
@@ -0,0 +1,123 @@
+use inkwell::{
+    module::Linkage,
+    types::StructType,
+    values::{IntValue, PointerValue},
+    AddressSpace,
+};
+
+use super::{value::extern_function, Compiler};
+
+/// Field of [`string_type`] that holds the length
+const LENGTH_FIELD: u32 = 3;
+
+/// Literals are never destroyed, no program holds them this many times
+const IMMORTAL: u64 = i64::MAX as u64 / 2;
+
+/// `{ reference count, drop function, class name, length, bytes }`, like `rl_string` in the
+/// runtime. The bytes are followed by a null terminator, so the standard library can use them
+pub fn string_type<'a>(compiler: &Compiler<'a>) -> StructType<'a> {
+    if let Some(x) = compiler.context.get_struct_type("string") {
+        return x;
+    }
+
+    let i64_type = compiler.context.i64_type();
+    let i8_ptr_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let string = compiler.context.opaque_struct_type("string");
+    string.set_body(
+        &[
+            i64_type.into(),
+            i8_ptr_type.into(),
+            i8_ptr_type.into(),
+            i64_type.into(),
+            compiler.context.i8_type().array_type(0).into(),
+        ],
+        false,
+    );
+    string
+}
+
+/// A string literal, stored in a global
+pub fn string_literal<'a>(compiler: &Compiler<'a>, value: &str) -> PointerValue<'a> {
+    let i64_type = compiler.context.i64_type();
+    let i8_ptr_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let literal = compiler.context.const_struct(
+        &[
+            i64_type.const_int(IMMORTAL, false).into(),
+            i8_ptr_type.const_null().into(),
+            i8_ptr_type.const_null().into(),
+            i64_type.const_int(value.len() as u64, false).into(),
+            compiler.context.const_string(value.as_bytes(), true).into(),
+        ],
+        false,
+    );
+
+    let global = compiler.module.add_global(literal.get_type(), None, ".str");
+    global.set_initializer(&literal);
+    global.set_linkage(Linkage::Private);
+    compiler
+        .builder
+        .build_pointer_cast(global.as_pointer_value(), i8_ptr_type, "string")
+}
+
+fn string_pointer<'a>(compiler: &Compiler<'a>, string: PointerValue<'a>) -> PointerValue<'a> {
+    compiler.builder.build_pointer_cast(
+        string,
+        string_type(compiler).ptr_type(AddressSpace::default()),
+        "string",
+    )
+}
+
+pub fn string_length<'a>(compiler: &Compiler<'a>, string: PointerValue<'a>) -> IntValue<'a> {
+    let ptr = compiler
+        .builder
+        .build_struct_gep(
+            string_type(compiler),
+            string_pointer(compiler, string),
+            LENGTH_FIELD,
+            "string_length",
+        )
+        .unwrap();
+    compiler
+        .builder
+        .build_load(compiler.context.i64_type(), ptr, "string_length")
+        .into_int_value()
+}
+
+/// The null terminated bytes of `string`
+pub fn string_bytes<'a>(compiler: &Compiler<'a>, string: PointerValue<'a>) -> PointerValue<'a> {
+    let ptr = compiler
+        .builder
+        .build_struct_gep(
+            string_type(compiler),
+            string_pointer(compiler, string),
+            LENGTH_FIELD + 1,
+            "string_bytes",
+        )
+        .unwrap();
+    compiler.builder.build_pointer_cast(
+        ptr,
+        compiler.context.i8_type().ptr_type(AddressSpace::default()),
+        "string_bytes",
+    )
+}
+
+/// `left ⨋ right`, a new string. Operands that nothing holds are destroyed
+pub fn concat<'a>(
+    compiler: &Compiler<'a>,
+    left: PointerValue<'a>,
+    right: PointerValue<'a>,
+) -> PointerValue<'a> {
+    let i8_ptr_type = compiler.context.i8_type().ptr_type(AddressSpace::default());
+    let concat = extern_function(
+        compiler,
+        "rl_string_concat",
+        i8_ptr_type.fn_type(&[i8_ptr_type.into(), i8_ptr_type.into()], false),
+    );
+    compiler
+        .builder
+        .build_call(concat, &[left.into(), right.into()], "concat")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value()
+}
@@ -0,0 +1,922 @@
+use core::fmt;
+
+use inkwell::{
+    types::{BasicType, BasicTypeEnum, FunctionType, StructType},
+    values::{BasicValueEnum, FloatValue, FunctionValue, IntValue, PointerValue, StructValue},
+    AddressSpace, FloatPredicate, IntPredicate,
+};
+
+use crate::{
+    bug,
+    parser::{ConditionalOperator, Foolean},
+    sema::types::{Signature, Type},
+};
+
+use super::{
+    class::{class_name, class_type, drop_function_name},
+    collection::{any_pointer, entry_alloca},
+    get_function,
+    string::{concat, string_bytes, string_length},
+    Compiler,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Value<'a> {
+    Number(FloatValue<'a>),
+    /// Bit-width of 1
+    Boolean(IntValue<'a>),
+    /// Bit-width of 8, holds a [`FooleanState`]
+    Foolean(IntValue<'a>),
+    /// A pointer to an `rl_string` of the runtime, laid out as [`string_type`]
+    String(PointerValue<'a>),
+    /// A pointer to an `rl_array` of the runtime
+    Array(PointerValue<'a>),
+    /// A pointer to an `rl_map` of the runtime
+    Map(PointerValue<'a>),
+    /// A dynamically typed value, laid out as [`any_type`]
+    Any(StructValue<'a>),
+    /// A pointer to an instance of the class with the struct type
+    Object(PointerValue<'a>, StructType<'a>),
+    Null,
+}
+
+/// What a [`Value`] is at compile time
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueKind {
+    Number,
+    Boolean,
+    Foolean,
+    String,
+    Array,
+    Map,
+    Null,
+    Any,
+    /// An instance of the class with this name
+    Object(String),
+}
+
+/// What a `Foolean` holds at runtime, `Yeet` picks one of these when it is evaluated
+#[derive(Clone, Copy)]
+enum FooleanState {
+    /// Only made by zero extending a `Boolean`, like `Yup`
+    #[allow(dead_code)]
+    Nope = 0,
+    Yup = 1,
+    Dunno = 2,
+    Huh = 3,
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueKind::Number => "Number",
+            ValueKind::Boolean => "Boolean",
+            ValueKind::Foolean => "Foolean",
+            ValueKind::String => "String",
+            ValueKind::Array => "Array",
+            ValueKind::Map => "Map",
+            ValueKind::Null => "wat",
+            ValueKind::Any => "Any",
+            ValueKind::Object(x) => x,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<&Type> for ValueKind {
+    fn from(r#type: &Type) -> Self {
+        match r#type {
+            Type::Number => ValueKind::Number,
+            Type::Boolean => ValueKind::Boolean,
+            Type::Foolean => ValueKind::Foolean,
+            Type::String => ValueKind::String,
+            Type::Array(_) => ValueKind::Array,
+            Type::Map => ValueKind::Map,
+            Type::Null => ValueKind::Null,
+            Type::Any => ValueKind::Any,
+            Type::Object(x) => ValueKind::Object(x.to_string()),
+        }
+    }
+}
+
+impl ValueKind {
+    /// Whether `other` can be used where a `self` is expected. Dynamically typed values are assumed to match
+    /// and objects can be `wat`, a `Foolean` holds it as `Dunno`
+    pub fn accepts(&self, other: &ValueKind) -> bool {
+        self == other
+            || *self == ValueKind::Any
+            || *other == ValueKind::Any
+            || (*self == ValueKind::Foolean && *other == ValueKind::Boolean)
+            || (matches!(self, ValueKind::Object(_) | ValueKind::Foolean)
+                && *other == ValueKind::Null)
+    }
+
+    /// The tag stored in the first field of an [`any_type`]
+    fn tag(&self) -> u64 {
+        match self {
+            ValueKind::Null => 0,
+            ValueKind::Number => 1,
+            ValueKind::Boolean => 2,
+            ValueKind::String => 3,
+            ValueKind::Array => 4,
+            ValueKind::Foolean => 5,
+            ValueKind::Object(_) => 6,
+            ValueKind::Map => 7,
+            ValueKind::Any => bug!("ANY_HAS_NO_TAG"),
+        }
+    }
+
+    pub fn llvm_type<'a>(&self, compiler: &Compiler<'a>) -> BasicTypeEnum<'a> {
+        match self {
+            ValueKind::Number => compiler.context.f64_type().into(),
+            ValueKind::Boolean => compiler.context.bool_type().into(),
+            ValueKind::Foolean => compiler.context.i8_type().into(),
+            ValueKind::String | ValueKind::Array | ValueKind::Map => compiler
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::default())
+                .into(),
+            ValueKind::Any => any_type(compiler).into(),
+            ValueKind::Object(x) => class_type(compiler, x)
+                .ptr_type(AddressSpace::default())
+                .into(),
+            // Annotations and arguments can not be `wat`, a `wat` return type means nothing is returned
+            ValueKind::Null => bug!("NULL_HAS_NO_TYPE"),
+        }
+    }
+}
+
+impl Signature {
+    /// A `wat` return type means the function returns nothing
+    pub fn fn_type<'a>(&self, compiler: &Compiler<'a>) -> FunctionType<'a> {
+        let params: Vec<_> = self
+            .params
+            .iter()
+            .map(|x| ValueKind::from(x).llvm_type(compiler).into())
+            .collect();
+
+        match ValueKind::from(&self.r#return) {
+            ValueKind::Null => compiler.context.void_type().fn_type(&params, false),
+            x => x.llvm_type(compiler).fn_type(&params, false),
+        }
+    }
+}
+
+impl<'a> Value<'a> {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Number(_) => ValueKind::Number,
+            Value::Boolean(_) => ValueKind::Boolean,
+            Value::Foolean(_) => ValueKind::Foolean,
+            Value::String(_) => ValueKind::String,
+            Value::Array(_) => ValueKind::Array,
+            Value::Map(_) => ValueKind::Map,
+            Value::Any(_) => ValueKind::Any,
+            Value::Object(_, x) => ValueKind::Object(class_name(*x)),
+            Value::Null => ValueKind::Null,
+        }
+    }
+
+    /// Rebuilds a value of `kind` from a register, such as a call result
+    pub fn from_basic_value(
+        compiler: &Compiler<'a>,
+        value: BasicValueEnum<'a>,
+        kind: &ValueKind,
+    ) -> Self {
+        match kind {
+            ValueKind::Number => Value::Number(value.into_float_value()),
+            ValueKind::Boolean => Value::Boolean(value.into_int_value()),
+            ValueKind::Foolean => Value::Foolean(value.into_int_value()),
+            ValueKind::String => Value::String(value.into_pointer_value()),
+            ValueKind::Array => Value::Array(value.into_pointer_value()),
+            ValueKind::Map => Value::Map(value.into_pointer_value()),
+            ValueKind::Any => Value::Any(value.into_struct_value()),
+            ValueKind::Object(x) => {
+                Value::Object(value.into_pointer_value(), class_type(compiler, x))
+            }
+            ValueKind::Null => Value::Null,
+        }
+    }
+
+    pub fn into_basic_value(self) -> BasicValueEnum<'a> {
+        match self {
+            Value::Number(x) => x.into(),
+            Value::Boolean(x) => x.into(),
+            Value::Foolean(x) => x.into(),
+            Value::String(x) | Value::Array(x) | Value::Map(x) => x.into(),
+            Value::Any(x) => x.into(),
+            Value::Object(x, _) => x.into(),
+            Value::Null => bug!("NULL_HAS_NO_VALUE"),
+        }
+    }
+}
+
+/// Declares a libc or runtime function the first time it is used
+pub fn extern_function<'a>(
+    compiler: &Compiler<'a>,
+    name: &str,
+    fn_type: FunctionType<'a>,
+) -> FunctionValue<'a> {
+    compiler
+        .module
+        .get_function(name)
+        .unwrap_or_else(|| compiler.module.add_function(name, fn_type, None))
+}
+
+/// `{ tag, data }`. `data` holds the bits of the value, or a pointer to it
+pub fn any_type<'a>(compiler: &Compiler<'a>) -> StructType<'a> {
+    if let Some(x) = compiler.context.get_struct_type("any") {
+        return x;
+    }
+
+    let i64_type = compiler.context.i64_type();
+    let any = compiler.context.opaque_struct_type("any");
+    any.set_body(&[compiler.context.i8_type().into(), i64_type.into()], false);
+    any
+}
+
+/// Not a tag, `rl_check_type` gets it for arrays whose items can be anything
+const TAG_ANY: u64 = 255;
+
+/// Shoots a bullet when the dynamically typed `value` is not a `r#type`, only if the compiler
+/// `checks`. The items of arrays are checked too, so arrays are checked even when they are not
+/// dynamically typed. `expected` describes the `damn` type for the bullet, which adds what it got
+pub fn check_kind<'a>(
+    compiler: &Compiler<'a>,
+    value: Value<'a>,
+    r#type: &Type,
+    expected: impl FnOnce() -> String,
+) {
+    let item = match r#type {
+        Type::Array(x) if **x != Type::Any => Some(&**x),
+        _ => None,
+    };
+    let checked = match value {
+        Value::Any(_) => *r#type != Type::Any,
+        Value::Array(_) => item.is_some(),
+        _ => false,
+    };
+    if !compiler.checks || !checked {
+        return;
+    }
+
+    let i8_type = compiler.context.i8_type();
+    let i8_ptr_type = i8_type.ptr_type(AddressSpace::default());
+    let any_ptr_type = any_type(compiler).ptr_type(AddressSpace::default());
+    let check = extern_function(
+        compiler,
+        "rl_check_type",
+        compiler.context.void_type().fn_type(
+            &[
+                any_ptr_type.into(),
+                i8_type.into(),
+                i8_ptr_type.into(),
+                i8_type.into(),
+                i8_ptr_type.into(),
+                i8_ptr_type.into(),
+            ],
+            false,
+        ),
+    );
+
+    // Classes are told apart by their drop function
+    let drop = |kind: &ValueKind| match kind {
+        ValueKind::Object(x) => compiler.builder.build_pointer_cast(
+            get_function(compiler, &drop_function_name(x))
+                .as_global_value()
+                .as_pointer_value(),
+            i8_ptr_type,
+            "drop",
+        ),
+        _ => i8_ptr_type.const_null(),
+    };
+    let kind = ValueKind::from(r#type);
+    let (item_tag, item_drop) = match item {
+        Some(x) => {
+            let item_kind = ValueKind::from(x);
+            (item_kind.tag(), drop(&item_kind))
+        }
+        None => (TAG_ANY, i8_ptr_type.const_null()),
+    };
+
+    let value = any_pointer(compiler, value, "checked");
+    let expected = compiler
+        .builder
+        .build_global_string_ptr(&expected(), ".expected")
+        .as_pointer_value();
+    compiler.builder.build_call(
+        check,
+        &[
+            value.into(),
+            i8_type.const_int(kind.tag(), false).into(),
+            drop(&kind).into(),
+            i8_type.const_int(item_tag, false).into(),
+            item_drop.into(),
+            expected.into(),
+        ],
+        "",
+    );
+}
+
+/// Converts `value` to `kind`, boxing into or unboxing out of [`Value::Any`] when needed
+pub fn coerce<'a>(compiler: &Compiler<'a>, value: Value<'a>, kind: &ValueKind) -> Value<'a> {
+    match (value, kind) {
+        (Value::Any(x), ValueKind::Any) => Value::Any(x),
+        (value, ValueKind::Any) => Value::Any(box_value(compiler, value)),
+        (Value::Any(x), kind) => unbox_value(compiler, x, kind),
+        (Value::Boolean(x), ValueKind::Foolean) => {
+            Value::Foolean(compiler.builder.build_int_z_extend(
+                x,
+                compiler.context.i8_type(),
+                "boolean_to_foolean",
+            ))
+        }
+        (Value::Null, ValueKind::Object(x)) => {
+            let class_type = class_type(compiler, x);
+            Value::Object(
+                class_type.ptr_type(AddressSpace::default()).const_null(),
+                class_type,
+            )
+        }
+        (Value::Null, ValueKind::Foolean) => Value::Foolean(
+            compiler
+                .context
+                .i8_type()
+                .const_int(FooleanState::Dunno as u64, false),
+        ),
+        (value, kind) if value.kind() == *kind => value,
+        (value, kind) => bug!("COERCE({} TO {})", value.kind(), kind),
+    }
+}
+
+/// Puts `value` in a [`Value::Any`], which can hold anything
+pub fn box_value<'a>(compiler: &Compiler<'a>, value: Value<'a>) -> StructValue<'a> {
+    if let Value::Any(x) = value {
+        return x;
+    }
+
+    let i64_type = compiler.context.i64_type();
+    let i8_type = compiler.context.i8_type();
+    let tag = match value {
+        // An object variable that holds `wat` is boxed as `wat`
+        Value::Object(x, _) => compiler
+            .builder
+            .build_select(
+                compiler.builder.build_is_null(x, "is_wat"),
+                i8_type.const_int(ValueKind::Null.tag(), false),
+                i8_type.const_int(value.kind().tag(), false),
+                "box_tag",
+            )
+            .into_int_value(),
+        _ => i8_type.const_int(value.kind().tag(), false),
+    };
+
+    let data = match value {
+        Value::Number(x) => compiler
+            .builder
+            .build_bitcast(x, i64_type, "box_number")
+            .into_int_value(),
+        Value::Boolean(x) => compiler
+            .builder
+            .build_int_z_extend(x, i64_type, "box_boolean"),
+        Value::Foolean(x) => compiler
+            .builder
+            .build_int_z_extend(x, i64_type, "box_foolean"),
+        Value::String(x) => compiler.builder.build_ptr_to_int(x, i64_type, "box_string"),
+        Value::Object(x, _) => compiler.builder.build_ptr_to_int(x, i64_type, "box_object"),
+        Value::Array(x) => compiler.builder.build_ptr_to_int(x, i64_type, "box_array"),
+        Value::Map(x) => compiler.builder.build_ptr_to_int(x, i64_type, "box_map"),
+        Value::Null => i64_type.const_zero(),
+        Value::Any(_) => unreachable!(),
+    };
+
+    let builder = &compiler.builder;
+    let any = any_type(compiler).get_undef();
+    let any = builder.build_insert_value(any, tag, 0, "box").unwrap();
+    builder
+        .build_insert_value(any, data, 1, "box")
+        .unwrap()
+        .into_struct_value()
+}
+
+fn unbox_value<'a>(compiler: &Compiler<'a>, any: StructValue<'a>, kind: &ValueKind) -> Value<'a> {
+    let builder = &compiler.builder;
+    let data = builder
+        .build_extract_value(any, 1, "unbox_data")
+        .unwrap()
+        .into_int_value();
+
+    match kind {
+        ValueKind::Number => Value::Number(
+            builder
+                .build_bitcast(data, compiler.context.f64_type(), "unbox_number")
+                .into_float_value(),
+        ),
+        ValueKind::Boolean => Value::Boolean(builder.build_int_truncate(
+            data,
+            compiler.context.bool_type(),
+            "unbox_boolean",
+        )),
+        ValueKind::Foolean => Value::Foolean(builder.build_int_truncate(
+            data,
+            compiler.context.i8_type(),
+            "unbox_foolean",
+        )),
+        ValueKind::String => Value::String(builder.build_int_to_ptr(
+            data,
+            compiler.context.i8_type().ptr_type(AddressSpace::default()),
+            "unbox_string",
+        )),
+        ValueKind::Object(x) => {
+            let class_type = class_type(compiler, x);
+            Value::Object(
+                builder.build_int_to_ptr(
+                    data,
+                    class_type.ptr_type(AddressSpace::default()),
+                    "unbox_object",
+                ),
+                class_type,
+            )
+        }
+        ValueKind::Array => Value::Array(builder.build_int_to_ptr(
+            data,
+            compiler.context.i8_type().ptr_type(AddressSpace::default()),
+            "unbox_array",
+        )),
+        ValueKind::Map => Value::Map(builder.build_int_to_ptr(
+            data,
+            compiler.context.i8_type().ptr_type(AddressSpace::default()),
+            "unbox_map",
+        )),
+        ValueKind::Null => Value::Null,
+        ValueKind::Any => Value::Any(any),
+    }
+}
+
+/// Numbers are used as is, dynamically typed values shoot a bullet when they do not hold one
+pub fn to_number<'a>(compiler: &Compiler<'a>, value: Value<'a>) -> FloatValue<'a> {
+    match value {
+        Value::Number(x) => x,
+        // The runtime checks that it holds a Number
+        Value::Any(x) => {
+            let any_ptr_type = any_type(compiler).ptr_type(AddressSpace::default());
+            let to_number = extern_function(
+                compiler,
+                "rl_to_number",
+                compiler
+                    .context
+                    .f64_type()
+                    .fn_type(&[any_ptr_type.into()], false),
+            );
+            let value = any_pointer(compiler, Value::Any(x), "value");
+            compiler
+                .builder
+                .build_call(to_number, &[value.into()], "number")
+                .try_as_basic_value()
+                .left()
+                .unwrap()
+                .into_float_value()
+        }
+        x => bug!("MATH_WITH({})", x.kind()),
+    }
+}
+
+/// `left ⨋ right` adds numbers and joins strings. With a dynamically typed operand the runtime
+/// decides which, and shoots a bullet when it can not do either
+pub fn add<'a>(compiler: &Compiler<'a>, left: Value<'a>, right: Value<'a>) -> Value<'a> {
+    match (left, right) {
+        (Value::String(l), Value::String(r)) => Value::String(concat(compiler, l, r)),
+        (Value::Any(_), Value::Any(_) | Value::Number(_) | Value::String(_))
+        | (Value::Number(_) | Value::String(_), Value::Any(_)) => {
+            let any_type = any_type(compiler);
+            let any_ptr_type = any_type.ptr_type(AddressSpace::default());
+            let add = extern_function(
+                compiler,
+                "rl_add",
+                compiler.context.void_type().fn_type(
+                    &[
+                        any_ptr_type.into(),
+                        any_ptr_type.into(),
+                        any_ptr_type.into(),
+                    ],
+                    false,
+                ),
+            );
+
+            let left = any_pointer(compiler, left, "left");
+            let right = any_pointer(compiler, right, "right");
+            let sum = entry_alloca(compiler, any_type.into(), "sum");
+            compiler
+                .builder
+                .build_call(add, &[left.into(), right.into(), sum.into()], "");
+
+            let sum = compiler.builder.build_load(any_type, sum, "sum");
+            Value::Any(sum.into_struct_value())
+        }
+        (Value::String(_), x) | (x, Value::String(_)) => bug!("ADD_STRING({})", x.kind()),
+        (left, right) => Value::Number(compiler.builder.build_float_add(
+            to_number(compiler, left),
+            to_number(compiler, right),
+            "expr_add",
+        )),
+    }
+}
+
+/// Lowers a `Foolean` literal, `Yeet` is left to the runtime
+pub fn foolean<'a>(compiler: &Compiler<'a>, foolean: &Foolean) -> Value<'a> {
+    let i8_type = compiler.context.i8_type();
+    let state = match foolean {
+        Foolean::Dunno => FooleanState::Dunno,
+        Foolean::Huh => FooleanState::Huh,
+        Foolean::Yeet => {
+            let yeet = extern_function(compiler, "rl_yeet", i8_type.fn_type(&[], false));
+            let state = compiler
+                .builder
+                .build_call(yeet, &[], "yeet")
+                .try_as_basic_value()
+                .left()
+                .unwrap()
+                .into_int_value();
+            return Value::Foolean(state);
+        }
+    };
+    Value::Foolean(i8_type.const_int(state as u64, false))
+}
+
+fn foolean_is<'a>(
+    compiler: &Compiler<'a>,
+    foolean: IntValue<'a>,
+    state: FooleanState,
+) -> IntValue<'a> {
+    compiler.builder.build_int_compare(
+        IntPredicate::EQ,
+        foolean,
+        foolean.get_type().const_int(state as u64, false),
+        "foolean_is",
+    )
+}
+
+pub fn to_boolean<'a>(compiler: &Compiler<'a>, value: Value<'a>) -> IntValue<'a> {
+    let float = |x: FloatValue<'a>| {
+        compiler.builder.build_float_compare(
+            FloatPredicate::ONE,
+            x,
+            x.get_type().const_zero(),
+            "expr_truthy",
+        )
+    };
+
+    let int = |x: IntValue<'a>| {
+        compiler.builder.build_int_compare(
+            IntPredicate::NE,
+            x,
+            x.get_type().const_zero(),
+            "expr_truthy",
+        )
+    };
+
+    match value {
+        Value::Number(x) => float(x),
+        Value::Boolean(x) => x, // Is already a 0 or a 1
+        Value::Foolean(x) => foolean_is(compiler, x, FooleanState::Yup),
+        Value::Null => compiler.context.bool_type().const_zero(),
+        Value::String(x) => int(string_length(compiler, x)),
+        Value::Object(x, _) => compiler.builder.build_is_not_null(x, "expr_truthy"),
+        // Arrays and maps are truthy when they have items, like strings
+        Value::Array(_) | Value::Map(_) => int(any_length(compiler, box_value(compiler, value))),
+        Value::Any(x) => {
+            // Decided at runtime by the tag
+            let builder = &compiler.builder;
+            let tag = builder
+                .build_extract_value(x, 0, "any_tag")
+                .unwrap()
+                .into_int_value();
+            let tag_is = |kind: ValueKind| {
+                builder.build_int_compare(
+                    IntPredicate::EQ,
+                    tag,
+                    tag.get_type().const_int(kind.tag(), false),
+                    "any_is",
+                )
+            };
+
+            let number = match unbox_value(compiler, x, &ValueKind::Number) {
+                Value::Number(x) => float(x),
+                _ => unreachable!(),
+            };
+            // Null has no data and booleans are zero extended
+            let data = builder
+                .build_extract_value(x, 1, "any_data")
+                .unwrap()
+                .into_int_value();
+            let other = int(data);
+            let foolean = builder.build_int_compare(
+                IntPredicate::EQ,
+                data,
+                data.get_type().const_int(FooleanState::Yup as u64, false),
+                "any_truthy",
+            );
+
+            let other = builder
+                .build_select(tag_is(ValueKind::Foolean), foolean, other, "any_truthy")
+                .into_int_value();
+            let length = int(any_length(compiler, x));
+            let mut truthy = other;
+            for kind in [ValueKind::String, ValueKind::Array, ValueKind::Map] {
+                truthy = builder
+                    .build_select(tag_is(kind), length, truthy, "any_truthy")
+                    .into_int_value();
+            }
+            builder
+                .build_select(tag_is(ValueKind::Number), number, truthy, "any_truthy")
+                .into_int_value()
+        }
+    }
+}
+
+/// The length of a dynamically typed string, array or map, otherwise 0
+fn any_length<'a>(compiler: &Compiler<'a>, any: StructValue<'a>) -> IntValue<'a> {
+    let tag = compiler
+        .builder
+        .build_extract_value(any, 0, "any_tag")
+        .unwrap();
+    let data = compiler
+        .builder
+        .build_extract_value(any, 1, "any_data")
+        .unwrap();
+    let length_type = compiler
+        .context
+        .i64_type()
+        .fn_type(&[tag.get_type().into(), data.get_type().into()], false);
+    let length = extern_function(compiler, "rl_length_any", length_type);
+
+    compiler
+        .builder
+        .build_call(length, &[tag.into(), data.into()], "length")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value()
+}
+
+/// `memcmp` of the first `length` bytes, sign extended to an i64
+fn compare_bytes<'a>(
+    compiler: &Compiler<'a>,
+    left: PointerValue<'a>,
+    right: PointerValue<'a>,
+    length: IntValue<'a>,
+) -> IntValue<'a> {
+    let memcmp_type = compiler.context.i32_type().fn_type(
+        &[
+            left.get_type().into(),
+            right.get_type().into(),
+            length.get_type().into(),
+        ],
+        false,
+    );
+    let memcmp = extern_function(compiler, "memcmp", memcmp_type);
+
+    let order = compiler
+        .builder
+        .build_call(
+            memcmp,
+            &[left.into(), right.into(), length.into()],
+            "memcmp",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    compiler
+        .builder
+        .build_int_s_extend(order, compiler.context.i64_type(), "memcmp_order")
+}
+
+/// Lexicographic order of two strings, negative when `left` comes first and zero when equal
+fn compare_strings<'a>(
+    compiler: &Compiler<'a>,
+    left: PointerValue<'a>,
+    right: PointerValue<'a>,
+) -> IntValue<'a> {
+    let builder = &compiler.builder;
+    let left_length = string_length(compiler, left);
+    let right_length = string_length(compiler, right);
+    let left_is_shorter =
+        builder.build_int_compare(IntPredicate::ULT, left_length, right_length, "shorter");
+    let common_length = builder
+        .build_select(left_is_shorter, left_length, right_length, "common_length")
+        .into_int_value();
+
+    let order = compare_bytes(
+        compiler,
+        string_bytes(compiler, left),
+        string_bytes(compiler, right),
+        common_length,
+    );
+    let is_prefix = builder.build_int_compare(
+        IntPredicate::EQ,
+        order,
+        order.get_type().const_zero(),
+        "is_prefix",
+    );
+
+    // When one string starts with the other, the shorter one comes first
+    let length_order = builder.build_int_sub(left_length, right_length, "length_order");
+    builder
+        .build_select(is_prefix, length_order, order, "string_order")
+        .into_int_value()
+}
+
+/// Compares the tags and contents of two dynamically typed values
+fn any_equals<'a>(
+    compiler: &Compiler<'a>,
+    left: StructValue<'a>,
+    right: StructValue<'a>,
+) -> IntValue<'a> {
+    let any_ptr_type = any_type(compiler).ptr_type(AddressSpace::default());
+    let equals_type = compiler
+        .context
+        .i8_type()
+        .fn_type(&[any_ptr_type.into(), any_ptr_type.into()], false);
+    let equals = extern_function(compiler, "rl_equals", equals_type);
+
+    let left = any_pointer(compiler, Value::Any(left), "left");
+    let right = any_pointer(compiler, Value::Any(right), "right");
+    let equals = compiler
+        .builder
+        .build_call(equals, &[left.into(), right.into()], "any_eq")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_int_value();
+    compiler
+        .builder
+        .build_int_truncate(equals, compiler.context.bool_type(), "any_eq")
+}
+
+/// Orders two dynamically typed values, the result is compared with 0
+fn any_compare<'a>(
+    compiler: &Compiler<'a>,
+    left: StructValue<'a>,
+    right: StructValue<'a>,
+) -> FloatValue<'a> {
+    let any_ptr_type = any_type(compiler).ptr_type(AddressSpace::default());
+    let compare_type = compiler
+        .context
+        .f64_type()
+        .fn_type(&[any_ptr_type.into(), any_ptr_type.into()], false);
+    let compare = extern_function(compiler, "rl_compare", compare_type);
+
+    let left = any_pointer(compiler, Value::Any(left), "left");
+    let right = any_pointer(compiler, Value::Any(right), "right");
+    compiler
+        .builder
+        .build_call(compare, &[left.into(), right.into()], "any_cmp")
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_float_value()
+}
+
+fn int_predicate(operator: &ConditionalOperator, signed: bool) -> IntPredicate {
+    match (operator, signed) {
+        (ConditionalOperator::Equality, _) => IntPredicate::EQ,
+        (ConditionalOperator::AntiEquality, _) => IntPredicate::NE,
+        (ConditionalOperator::GreaterThan, true) => IntPredicate::SGT,
+        (ConditionalOperator::GreaterThan, false) => IntPredicate::UGT,
+        (ConditionalOperator::GreaterThanOrEqual, true) => IntPredicate::SGE,
+        (ConditionalOperator::GreaterThanOrEqual, false) => IntPredicate::UGE,
+        (ConditionalOperator::LessThan, true) => IntPredicate::SLT,
+        (ConditionalOperator::LessThan, false) => IntPredicate::ULT,
+        (ConditionalOperator::LessThanOrEqual, true) => IntPredicate::SLE,
+        (ConditionalOperator::LessThanOrEqual, false) => IntPredicate::ULE,
+    }
+}
+
+fn float_predicate(operator: &ConditionalOperator) -> FloatPredicate {
+    match operator {
+        ConditionalOperator::Equality => FloatPredicate::OEQ,
+        ConditionalOperator::AntiEquality => FloatPredicate::UNE,
+        ConditionalOperator::GreaterThan => FloatPredicate::OGT,
+        ConditionalOperator::GreaterThanOrEqual => FloatPredicate::OGE,
+        ConditionalOperator::LessThan => FloatPredicate::OLT,
+        ConditionalOperator::LessThanOrEqual => FloatPredicate::OLE,
+    }
+}
+
+/// Compares two values, booleans are ordered `Nope` then `Yup` and strings lexicographically.
+/// Values of different kinds are never equal and can not be ordered
+pub fn compare<'a>(
+    compiler: &Compiler<'a>,
+    left: Value<'a>,
+    operator: &ConditionalOperator,
+    right: Value<'a>,
+) -> IntValue<'a> {
+    let is_equality = matches!(
+        operator,
+        ConditionalOperator::Equality | ConditionalOperator::AntiEquality
+    );
+
+    let (left, right) = match (left.kind(), right.kind()) {
+        (l, r) if l == r && l != ValueKind::Any => (left, right),
+        // `Dunno` is the `Foolean` version of `wat`
+        (ValueKind::Foolean, ValueKind::Null) | (ValueKind::Null, ValueKind::Foolean)
+            if is_equality =>
+        {
+            let foolean = match (left, right) {
+                (Value::Foolean(x), _) | (_, Value::Foolean(x)) => x,
+                _ => unreachable!(),
+            };
+            let equals = foolean_is(compiler, foolean, FooleanState::Dunno);
+            return match operator {
+                ConditionalOperator::Equality => equals,
+                _ => compiler.builder.build_not(equals, "foolean_ne"),
+            };
+        }
+        (ValueKind::Object(_), ValueKind::Null) if is_equality => {
+            (left, coerce(compiler, right, &left.kind()))
+        }
+        (ValueKind::Null, ValueKind::Object(_)) if is_equality => {
+            (coerce(compiler, left, &right.kind()), right)
+        }
+        (ValueKind::Foolean, ValueKind::Boolean) if is_equality => {
+            (left, coerce(compiler, right, &ValueKind::Foolean))
+        }
+        (ValueKind::Boolean, ValueKind::Foolean) if is_equality => {
+            (coerce(compiler, left, &ValueKind::Foolean), right)
+        }
+        _ if is_equality => {
+            // Decided at runtime by the tags
+            let equals = any_equals(
+                compiler,
+                box_value(compiler, left),
+                box_value(compiler, right),
+            );
+            return match operator {
+                ConditionalOperator::Equality => equals,
+                _ => compiler.builder.build_not(equals, "any_ne"),
+            };
+        }
+        // Ordered by the runtime, which checks the tags
+        (
+            ValueKind::Any,
+            ValueKind::Any | ValueKind::Number | ValueKind::String | ValueKind::Boolean,
+        )
+        | (ValueKind::Number | ValueKind::String | ValueKind::Boolean, ValueKind::Any) => {
+            let order = any_compare(
+                compiler,
+                box_value(compiler, left),
+                box_value(compiler, right),
+            );
+            return compiler.builder.build_float_compare(
+                float_predicate(operator),
+                order,
+                order.get_type().const_zero(),
+                "expr_cmp",
+            );
+        }
+        (ValueKind::Any, kind) => (coerce(compiler, left, &kind), right),
+        (kind, ValueKind::Any) => (left, coerce(compiler, right, &kind)),
+        (l, r) => bug!("COMPARE({} WITH {})", l, r),
+    };
+
+    let builder = &compiler.builder;
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => {
+            builder.build_float_compare(float_predicate(operator), l, r, "expr_cmp")
+        }
+        (Value::Boolean(l), Value::Boolean(r)) => {
+            builder.build_int_compare(int_predicate(operator, false), l, r, "expr_cmp")
+        }
+        (Value::String(l), Value::String(r)) => {
+            let order = compare_strings(compiler, l, r);
+            builder.build_int_compare(
+                int_predicate(operator, true),
+                order,
+                order.get_type().const_zero(),
+                "expr_cmp",
+            )
+        }
+        (Value::Foolean(l), Value::Foolean(r)) if is_equality => {
+            builder.build_int_compare(int_predicate(operator, false), l, r, "expr_cmp")
+        }
+        (Value::Object(l, _), Value::Object(r, _))
+        | (Value::Array(l), Value::Array(r))
+        | (Value::Map(l), Value::Map(r))
+            if is_equality =>
+        {
+            let i64_type = compiler.context.i64_type();
+            builder.build_int_compare(
+                int_predicate(operator, false),
+                builder.build_ptr_to_int(l, i64_type, "object_address"),
+                builder.build_ptr_to_int(r, i64_type, "object_address"),
+                "expr_cmp",
+            )
+        }
+        (Value::Null, Value::Null) if is_equality => compiler.context.bool_type().const_int(
+            matches!(operator, ConditionalOperator::Equality) as u64,
+            false,
+        ),
+        (x, _) => bug!("ORDER({})", x.kind()),
+    }
+}
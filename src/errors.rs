@@ -1,7 +1,123 @@
-use crate::Rule;
+use crate::{modules::SourceFile, parser::Span, Rule};
 use colored::Colorize;
 use core::fmt;
-use pest::error::LineColLocation;
+use pest::error::{Error, ErrorVariant, LineColLocation};
+use std::{panic::AssertUnwindSafe, rc::Rc};
+
+/// Whether a [`Diagnostic`] stops the program from compiling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found while parsing, checking or compiling a program. They are collected and shown
+/// once compiling stops
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Names the kind of problem, like `AntiJavaException`
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// The file the spans are in, `None` for problems that are not in the code
+    pub file: Option<Rc<SourceFile>>,
+    /// The code with the problem
+    pub primary: Option<Span>,
+    /// Other code that is part of the problem, with what it has to do with it
+    pub secondary: Vec<(Span, String)>,
+    pub notes: Vec<String>,
+    /// How to fix the problem
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            code: None,
+            message,
+            file: None,
+            primary: None,
+            secondary: vec![],
+            notes: vec![],
+            help: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message.into())
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message.into())
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.primary = Some(span);
+        self
+    }
+
+    /// Sets the file of the spans, unless it is already known
+    pub fn in_file(mut self, file: &Rc<SourceFile>) -> Self {
+        self.file.get_or_insert_with(|| file.clone());
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push((span, message.into()));
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Shows the problem under its code like a syntax error, followed by the related code, notes
+    /// and help
+    pub fn render(&self) -> String {
+        let message = match self.code {
+            Some(code) => format!("{}: {}", code, self.message),
+            None => self.message.to_string(),
+        };
+
+        let mut output = match (&self.file, self.primary) {
+            (Some(file), Some(span)) => format_source_error(file, span, message),
+            (Some(file), None) => format!("{}: {}\n", file.path, message),
+            (None, _) => format!("{}\n", message),
+        };
+        if let Some(file) = &self.file {
+            for (span, message) in &self.secondary {
+                output += &format_source_error(file, *span, message.to_string());
+            }
+        }
+
+        // Lined up with the `=` under the code
+        let padding = " ".repeat(self.primary.map_or(0, |x| x.start.line.to_string().len()));
+        let colored_eq = "=".blue().bold();
+        for note in &self.notes {
+            output += &format!("{} {} {} {}\n", padding, colored_eq, "note:".bold(), note);
+        }
+        if let Some(help) = &self.help {
+            output += &format!("{} {} {} {}\n", padding, colored_eq, "help:".bold(), help);
+        }
+        output
+    }
+}
 
 pub fn format_error(error: pest::error::Error<Rule>) -> String {
     let code = error.line();
@@ -17,14 +133,18 @@ pub fn format_error(error: pest::error::Error<Rule>) -> String {
     let colored_line = pos.0.to_string().blue().bold();
     let colored_col = pos.1.to_string().blue().bold();
 
-    let colored_error_position = format!("{}{}{}", colored_line, ":".blue().bold(), colored_col);
+    let mut colored_error_position =
+        format!("{}{}{}", colored_line, ":".blue().bold(), colored_col);
+    if let Some(path) = error.path() {
+        colored_error_position = format!("{}{}{}", path, ":".blue().bold(), colored_error_position);
+    }
 
     let colored_bar = "|".blue().bold();
     let colored_eq = "=".blue().bold();
 
     let colored_arrow = "-->".blue().bold();
     let colored_error_arrow = "^".red().bold();
-    return format!(
+    format!(
         r"
 {line_padding}{colored_arrow} {colored_error_position}
 {line_padding} {colored_bar}
@@ -33,25 +153,71 @@ pub fn format_error(error: pest::error::Error<Rule>) -> String {
 {line_padding} {colored_bar}
 {line_padding} {colored_eq} {message}
 "
-    );
+    )
 }
 
-pub fn syntax_error(syntax_error: pest::error::Error<Rule>) -> ! {
-    error!("{}", format_error(syntax_error));
+/// A syntax error that pest found, which stops the file from being parsed
+pub fn syntax_error(syntax_error: pest::error::Error<Rule>, file: &Rc<SourceFile>) -> Diagnostic {
+    let (start, end) = match syntax_error.location {
+        pest::error::InputLocation::Pos(x) => (x, x),
+        pest::error::InputLocation::Span(x) => x,
+    };
+    let span = match (
+        pest::Position::new(&file.code, start),
+        pest::Position::new(&file.code, end),
+    ) {
+        (Some(start), Some(end)) => Span {
+            start: start.into(),
+            end: end.into(),
+        },
+        _ => _bug(format_args!("SYNTAX_ERROR_OUTSIDE_FILE({})", file.path)),
+    };
+    Diagnostic::error(syntax_error.variant.message())
+        .with_span(span)
+        .in_file(file)
 }
 
 const ERR_BUG: &str =
-    "Error! This is a bug, please report this at https://github.com/elijah629/redditlang/issues. Make sure to include your code! Additional Information: ";
+    "This is a bug, please report this at https://github.com/elijah629/redditlang/issues. Make sure to include your code!";
+
+/// What [`bug!`] was given, carried from where the compiler hit it to [`catch_bug`]
+struct Bug(String);
 
 pub fn _bug(args: fmt::Arguments) -> ! {
-    error!("{}{}", ERR_BUG, args);
+    // Unwinds without a panic message, the bug is shown as a Diagnostic
+    std::panic::resume_unwind(Box::new(Bug(args.to_string())));
 }
 
+/// Runs `f`, returning a bug of the compiler it hits as a [`Diagnostic`] instead of crashing
+pub fn catch_bug<T>(f: impl FnOnce() -> T) -> Result<T, Diagnostic> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).map_err(|x| match x.downcast::<Bug>() {
+        Ok(bug) => bug_diagnostic(bug.0),
+        Err(x) => std::panic::resume_unwind(x),
+    })
+}
+
+/// Reports a bug of the compiler, `information` tells where it was hit
+pub fn bug_diagnostic(information: impl fmt::Display) -> Diagnostic {
+    Diagnostic::error(ERR_BUG)
+        .with_code("CompilerBug")
+        .with_note(format!("Additional Information: {}", information))
+}
+
+/// Stops the CLI. The compiler returns [`Diagnostic`]s instead, so it never exits the process
 pub fn _error(args: fmt::Arguments) -> ! {
     log::error!("{}", args);
     std::process::exit(1);
 }
 
+/// Shows `message` under the code at `span`, like a syntax error
+pub fn format_source_error(file: &SourceFile, span: Span, message: String) -> String {
+    let variant = ErrorVariant::CustomError { message };
+    match pest::Span::new(&file.code, span.start.offset, span.end.offset) {
+        Some(x) => format_error(Error::new_from_span(variant, x).with_path(&file.path)),
+        None => _bug(format_args!("SPAN_OUTSIDE_FILE({:?}, {})", span, file.path)),
+    }
+}
+
 #[macro_export]
 macro_rules! error {
     ($($arg:tt)*) => {{
@@ -65,5 +231,3 @@ macro_rules! bug {
         $crate::errors::_bug(std::format_args!($($arg)*));
     }};
 }
-
-pub(crate) use error;
@@ -13,8 +13,8 @@ pub fn hard_reset(repo: &Repository, remote: &str, branch: &str) -> Result<(), g
 }
 
 fn pull(repo: &Repository, remote: &str, branch: &str) -> Result<(), git2::Error> {
-    fetch(&repo, remote, branch)?;
-    hard_reset(&repo, remote, branch)?;
+    fetch(repo, remote, branch)?;
+    hard_reset(repo, remote, branch)?;
     Ok(())
 }
 
@@ -23,18 +23,18 @@ pub fn clone_else_pull<P: AsRef<Path>>(
     into: P,
     branch: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let cloned = Path::try_exists(&into.as_ref())?;
+    let cloned = Path::try_exists(into.as_ref())?;
     if cloned {
         let repo = Repository::open(&into)?;
-        pull(&repo, "origin", &branch)?;
+        pull(&repo, "origin", branch)?;
     } else {
-        Repository::clone(&url, &into)?;
+        Repository::clone(url, &into)?;
     };
     Ok(())
 }
 
 pub fn checkout(repo: &Repository, refname: &str) -> Result<(), Box<dyn std::error::Error>> {
-    repo.set_head(&refname)?;
+    repo.set_head(refname)?;
     repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
     Ok(())
 }
@@ -45,13 +45,13 @@ pub fn generate<P: AsRef<Path>>(
     branch: Option<&str>,
     into: P,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let repo = Repository::clone(&url, &into)?;
+    let repo = Repository::clone(url, &into)?;
 
     if let Some(branch) = branch {
         checkout(&repo, branch)?;
     }
 
-    fs::remove_dir_all(&into.as_ref().join(".git"))?;
+    fs::remove_dir_all(into.as_ref().join(".git"))?;
     Repository::init(&into)?;
     Ok(())
 }
@@ -1,10 +1,12 @@
+// Diagnostics are only made when a program has a problem, so their size does not matter
+#![allow(clippy::result_large_err)]
+
 use crate::{
     compiler::{
-        compile,
-        linking::{build_libstd, define_libstd, link},
-        CompileMetadata, Compiler, Scope,
+        compile_program,
+        linking::{build_libstd, link},
     },
-    errors::syntax_error,
+    errors::{catch_bug, Diagnostic},
     project::ProjectConfiguration,
 };
 use clap::{Parser, Subcommand};
@@ -15,13 +17,11 @@ use inkwell::{
     targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine},
     OptimizationLevel,
 };
-use parser::{parse, Tree};
-use pest::Parser as PestParser;
+use modules::load_modules;
 use pest_derive::Parser as PestParser;
 use project::Project;
 use semver::Version;
 use std::{
-    collections::HashMap,
     env, fs,
     hash::Hash,
     path::{Path, PathBuf},
@@ -32,9 +32,10 @@ pub mod compiler;
 pub mod errors;
 pub mod git;
 pub mod logger;
+pub mod modules;
 pub mod parser;
 pub mod project;
-pub mod utils;
+pub mod sema;
 
 #[derive(PestParser)]
 #[grammar = "../grammar.pest"]
@@ -110,7 +111,14 @@ fn main() {
     let args = Args::parse();
     logger::init().unwrap();
 
-    match args.command {
+    // The compiler returns the bugs it hits, this catches any others
+    if let Err(x) = catch_bug(|| run(args.command)) {
+        stop(&[x]);
+    }
+}
+
+fn run(command: Commands) {
+    match command {
         Commands::Cook {
             release,
             assembly,
@@ -153,7 +161,7 @@ fn main() {
             })
             .unwrap();
 
-            fs::write(&path.join("walter.yml"), yaml).unwrap();
+            fs::write(path.join("walter.yml"), yaml).unwrap();
         }
         Commands::Clean => {
             let project = get_current_project();
@@ -177,18 +185,33 @@ fn main() {
                 command.args(args);
             }
 
-            command.spawn().unwrap();
+            command.spawn().unwrap().wait().unwrap();
         }
     }
 }
 
-fn parse_file(file: &str) -> Tree {
-    match RLParser::parse(Rule::Program, file) {
-        Ok(x) => parse(x),
-        Err(x) => syntax_error(x),
+/// Shows every problem under its code
+fn show_diagnostics(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        if diagnostic.is_error() {
+            log::error!("{}", diagnostic.render());
+        } else {
+            log::warn!("{}", diagnostic.render());
+        }
     }
 }
 
+/// Shows every problem and stops compiling
+fn stop(diagnostics: &[Diagnostic]) -> ! {
+    show_diagnostics(diagnostics);
+    let errors = diagnostics.iter().filter(|x| x.is_error()).count();
+    error!(
+        "Can not compile because of {} error{}",
+        errors,
+        if errors == 1 { "" } else { "s" }
+    );
+}
+
 fn cook(release: bool, assembly: bool, no_std: bool, show_ir: bool) -> PathBuf {
     let project = get_current_project();
     let std_path = build_libstd().unwrap_or_else(|x| error!("Error building libstd: {:?}", x));
@@ -198,68 +221,38 @@ fn cook(release: bool, assembly: bool, no_std: bool, show_ir: bool) -> PathBuf {
         .join("build")
         .join(if release { "release" } else { "debug" });
     let src_dir = project_dir.join("src");
-    let main_file = src_dir.join("main.rl");
-    let main_file = fs::read_to_string(&main_file).unwrap();
 
     fs::create_dir_all(&build_dir).unwrap();
 
-    log::info!("Lexing/Parsing");
-
-    let tree = parse_file(&main_file);
+    // Problems that did not stop a file from being parsed are shown with the ones sema finds
+    let (modules, mut diagnostics) = load_modules(&src_dir).unwrap_or_else(|x| stop(&x));
+    log::info!("Checking");
+    let declarations = match sema::check(&modules) {
+        Ok((declarations, warnings)) => {
+            diagnostics.extend(warnings);
+            Some(declarations)
+        }
+        Err(x) => {
+            diagnostics.extend(x);
+            None
+        }
+    };
+    let declarations = match declarations {
+        Some(x) if !diagnostics.iter().any(Diagnostic::is_error) => x,
+        _ => stop(&diagnostics),
+    };
+    show_diagnostics(&diagnostics);
 
     log::info!("Compiling");
 
     let context = Context::create();
-    let module = context.create_module("main");
-    let builder = context.create_builder();
-
-    let compiler = Compiler {
-        context: &context,
-        module,
-        builder,
-    };
-
-    define_libstd(&compiler);
-
-    let entry_basic_block = {
-        let compiler = &compiler;
-        let main_type = compiler.context.i32_type().fn_type(&[], false);
-        let main_fn = compiler.module.add_function("main", main_type, None);
-
-        let entry_basic_block = compiler.context.append_basic_block(main_fn, "");
-        compiler.builder.position_at_end(entry_basic_block);
-        entry_basic_block
-    };
-    compile(
-        &compiler,
-        &tree,
-        &mut CompileMetadata {
-            basic_block: entry_basic_block,
-            function_scope: Scope {
-                variables: HashMap::new(),
-            },
-        },
-    );
-
-    // Add return
-    compiler
-        .builder
-        .build_return(Some(&compiler.context.i32_type().const_zero()));
+    let module =
+        compile_program(&context, &modules, declarations, !release).unwrap_or_else(|x| stop(&[x]));
 
     if show_ir {
-        println!("{}", &compiler.module.print_to_string().to_str().unwrap());
+        println!("{}", module.print_to_string().to_str().unwrap());
     }
 
-    // LLVM errors
-    if let Err(x) = compiler.module.verify() {
-        log::error!("│ {}", "Module verification failed".bold());
-        let lines: Vec<&str> = x.to_str().unwrap().lines().collect();
-        for line in &lines[0..lines.len() - 1] {
-            log::error!("│  {}", line);
-        }
-        error!("└─ {}\n", lines.last().unwrap());
-    };
-
     // TODO: allow user chosen targets
     Target::initialize_x86(&InitializationConfig::default());
 
@@ -286,13 +279,13 @@ fn cook(release: bool, assembly: bool, no_std: bool, show_ir: bool) -> PathBuf {
 
     target_machine
         .write_to_file(
-            &compiler.module,
+            &module,
             if assembly {
                 FileType::Assembly
             } else {
                 FileType::Object
             },
-            &object_path,
+            object_path,
         )
         .unwrap();
 
@@ -300,11 +293,12 @@ fn cook(release: bool, assembly: bool, no_std: bool, show_ir: bool) -> PathBuf {
 
     link(
         &project,
-        &target_triple,
+        target_triple,
         &build_dir,
-        &object_path,
+        object_path,
         &std_path,
         release,
         no_std,
     )
+    .unwrap_or_else(|x| stop(&[x]))
 }
@@ -0,0 +1,273 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use colored::Colorize;
+
+use crate::{
+    bug,
+    errors::{catch_bug, Diagnostic},
+    parser::{parse_file, parse_header, Node, Term, Tree},
+};
+
+/// The code of a file, with its path relative to the project
+#[derive(Debug, Clone, Default)]
+pub struct SourceFile {
+    pub path: String,
+    pub code: String,
+}
+
+/// A parsed source file
+pub struct SourceModule {
+    /// Path of the file in `src/` without `.rl`, `None` for `main.rl`
+    pub name: Option<String>,
+    pub file: Rc<SourceFile>,
+    pub tree: Tree,
+    /// Names of the modules it imports
+    pub imports: Vec<String>,
+}
+
+struct Loader {
+    src_dir: PathBuf,
+    /// Files by the name in their `subreddit` line, found when first needed
+    subreddits: Option<HashMap<String, PathBuf>>,
+    /// Names of the files loaded so far
+    loaded: HashMap<PathBuf, String>,
+    /// Files being loaded, each one imports the next
+    stack: Vec<PathBuf>,
+    modules: Vec<SourceModule>,
+    /// Problems found so far, loading goes on to find the others
+    diagnostics: Vec<Diagnostic>,
+    /// Files that could not be read or parsed, their problems are only reported once
+    failed: HashSet<PathBuf>,
+    /// Whether a file could not be loaded at all, checking the others would only report that
+    /// what it declares is missing
+    incomplete: bool,
+}
+
+/// Reads `src/main.rl` and every module it imports. Modules come before the files that import
+/// them, `main.rl` is last. They are returned with the problems found in them, unless a file could
+/// not be read, parsed or found at all
+pub fn load_modules(
+    src_dir: &Path,
+) -> Result<(Vec<SourceModule>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let src_dir = match src_dir.canonicalize() {
+        Ok(x) => x,
+        Err(_) => {
+            let message = format!("Can not find {}", src_dir.display());
+            return Err(vec![Diagnostic::error(message)]);
+        }
+    };
+    let mut loader = Loader {
+        src_dir: src_dir.clone(),
+        subreddits: None,
+        loaded: HashMap::new(),
+        stack: vec![],
+        modules: vec![],
+        diagnostics: vec![],
+        failed: HashSet::new(),
+        incomplete: false,
+    };
+
+    let main_file = match src_dir.join("main.rl").canonicalize() {
+        Ok(x) => x,
+        Err(_) => {
+            let message = format!("Can not find {}", "src/main.rl".bold());
+            return Err(vec![Diagnostic::error(message)]);
+        }
+    };
+    catch_bug(|| loader.load(&main_file)).map_err(|x| vec![x])?;
+    if loader.incomplete {
+        Err(loader.diagnostics)
+    } else {
+        Ok((loader.modules, loader.diagnostics))
+    }
+}
+
+impl Loader {
+    /// Loads `path` and its imports if it was not already, returning the name of its module. A
+    /// file that could not be parsed is not added to the modules
+    fn load(&mut self, path: &Path) -> String {
+        let name = self.module_name(path);
+        if let Some(name) = self.loaded.get(path) {
+            return name.to_string();
+        }
+        self.loaded.insert(path.to_path_buf(), name.to_string());
+
+        log::info!("Lexing/Parsing {}", self.relative_path(path).bold());
+        let (file, tree) = match self.parse(path) {
+            Some(x) => x,
+            None => return name,
+        };
+
+        self.stack.push(path.to_path_buf());
+        let mut imports = vec![];
+        for node in &tree {
+            let import = match node {
+                Node::Import(x) => x,
+                _ => continue,
+            };
+            let module = match &import.path {
+                Term::String(x) => x,
+                _ => bug!("IMPORT_PATH_NOT_STRING"),
+            };
+
+            let import_path = match self.find(module) {
+                Some(x) => x,
+                None => {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("Can not find module `{}`", module))
+                            .with_span(import.span)
+                            .in_file(&file)
+                            .with_help(format!(
+                                "Add src/{}.rl or a file with `subreddit r/{}`",
+                                module, module
+                            )),
+                    );
+                    self.incomplete = true;
+                    continue;
+                }
+            };
+            if let Some(start) = self.stack.iter().position(|x| *x == import_path) {
+                let cycle: Vec<String> = self.stack[start..]
+                    .iter()
+                    .chain([&import_path])
+                    .map(|x| self.module_name(x))
+                    .collect();
+                self.diagnostics.push(
+                    Diagnostic::error("Modules import each other")
+                        .with_span(import.span)
+                        .in_file(&file)
+                        .with_note(cycle.join(" -> ")),
+                );
+                self.incomplete = true;
+                continue;
+            }
+            imports.push(self.load(&import_path));
+        }
+        self.stack.pop();
+
+        self.modules.push(SourceModule {
+            name: if self.stack.is_empty() {
+                None
+            } else {
+                Some(name.to_string())
+            },
+            file,
+            tree,
+            imports,
+        });
+        name
+    }
+
+    /// Reads and parses `path`. Its problems are reported the first time it is parsed, a file with
+    /// problems that could still be parsed is returned
+    fn parse(&mut self, path: &Path) -> Option<(Rc<SourceFile>, Tree)> {
+        let result = match fs::read_to_string(path) {
+            Ok(code) => {
+                let file = Rc::new(SourceFile {
+                    path: self.relative_path(path),
+                    code,
+                });
+                parse_file(&file).map(|(tree, diagnostics)| (file, tree, diagnostics))
+            }
+            Err(x) => Err(vec![Diagnostic::error(format!(
+                "Can not read {}: {}",
+                path.display(),
+                x
+            ))]),
+        };
+
+        match result {
+            Ok((file, tree, diagnostics)) => {
+                self.diagnostics.extend(diagnostics);
+                Some((file, tree))
+            }
+            Err(x) => {
+                if self.failed.insert(path.to_path_buf()) {
+                    self.diagnostics.extend(x);
+                }
+                self.incomplete = true;
+                None
+            }
+        }
+    }
+
+    /// The file `weneed "<module>"` refers to, `src/<module>.rl` or the file with
+    /// `subreddit r/<module>`
+    fn find(&mut self, module: &str) -> Option<PathBuf> {
+        let path = self.src_dir.join(format!("{}.rl", module));
+        if let Ok(path) = path.canonicalize() {
+            if path.starts_with(&self.src_dir) {
+                return Some(path);
+            }
+        }
+
+        if self.subreddits.is_none() {
+            let subreddits = self.find_subreddits();
+            self.subreddits = Some(subreddits);
+        }
+        self.subreddits.as_ref().unwrap().get(module).cloned()
+    }
+
+    /// Every `.rl` file in `src/` by the name in its `subreddit` line. Only that line is read, so
+    /// files that are not imported do not have to compile
+    fn find_subreddits(&mut self) -> HashMap<String, PathBuf> {
+        let mut subreddits = HashMap::new();
+        for path in rl_files(&self.src_dir) {
+            let path = path.canonicalize().unwrap();
+            let code = match fs::read_to_string(&path) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            let file = Rc::new(SourceFile {
+                path: self.relative_path(&path),
+                code,
+            });
+            if let Some(module) = parse_header(&file) {
+                if let Some(other) = subreddits.insert(module.ident.0.to_string(), path.clone()) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "`subreddit r/{}` is in more than one file",
+                            module.ident.0
+                        ))
+                        .with_span(module.span)
+                        .in_file(&file)
+                        .with_note(format!("It is also in {}", self.relative_path(&other))),
+                    );
+                }
+            }
+        }
+        subreddits
+    }
+
+    fn relative_path(&self, path: &Path) -> String {
+        match path.strip_prefix(&self.src_dir) {
+            Ok(x) => Path::new("src").join(x).display().to_string(),
+            Err(_) => path.display().to_string(),
+        }
+    }
+
+    fn module_name(&self, path: &Path) -> String {
+        match path.strip_prefix(&self.src_dir) {
+            Ok(x) => x.with_extension("").display().to_string(),
+            Err(_) => bug!("MODULE_OUTSIDE_SRC({})", path.display()),
+        }
+    }
+}
+
+fn rl_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(rl_files(&path));
+        } else if path.extension().is_some_and(|x| x == "rl") {
+            files.push(path);
+        }
+    }
+    files
+}
@@ -1,90 +1,96 @@
-use crate::errors::syntax_error;
+use crate::errors::Diagnostic;
 use crate::parser::{
-    parse, parse_one, Assignment, BinaryExpr, BinaryExprTerm, Break, Call, Catch, Class,
-    ConditionExprTerm, ConditionalExpr, ConditionalOperator, Declaration, Else, Expr, Function,
-    FunctionMod, Ident, IfBlock, IfCase, IfNode, Import, Index, IndexExpr, Loop, MathOperator,
-    Module, Node, Number, Return, Term, Throw, Tree, Try, TryCatch, Type, Variable, VariableMod,
+    parse, parse_one, Assignment, BinaryExpr, Break, Call, Catch, Class, ClassMod,
+    ConditionExprTerm, ConditionalExpr, ConditionalOperator, Declaration, Each, Else, Expr,
+    Foolean, Function, FunctionMod, Ident, IfBlock, IfCase, IfNode, Import, IndexExpr, Loop,
+    MathOperator, Member, Module, Node, Number, Return, Span, Term, Throw, Tree, Try, TryCatch,
+    Type, UnaryExpr, UnaryOperator, Variable, VariableMod,
 };
-use crate::utils::is_unique;
 use crate::{bug, Rule};
-use pest::error::Error;
 use pest::iterators::Pair;
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+use std::sync::OnceLock;
 
+/// Problems that do not stop parsing, like a long identifier, are added to `diagnostics` and the
+/// node is still built
 pub trait Parse {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self>
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self>
     where
         Self: Sized;
 }
 
 impl Parse for Declaration {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
-        let ident = Ident::parse_from(inner.next().unwrap()).unwrap();
-        let r#type = inner.next().map(|x| x.into_inner()).map(|mut x| Type {
-            ident: Ident::parse_from(x.next().unwrap()).unwrap(),
-            is_array: x.next().is_some(),
+        let ident = Ident::parse_from(inner.next().unwrap(), diagnostics).unwrap();
+        let r#type = inner.next().map(|x| {
+            let span = x.as_span().into();
+            let mut inner = x.into_inner();
+            Type {
+                ident: Ident::parse_from(inner.next().unwrap(), diagnostics).unwrap(),
+                is_array: inner.next().is_some(),
+                span,
+            }
         });
 
-        Some(Self { ident, r#type })
+        Some(Self {
+            ident,
+            r#type,
+            span,
+        })
     }
 }
 
 impl Parse for Function {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
-        let start_pos = pair.as_span().start_pos();
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
         let modifiers: Vec<FunctionMod> = inner
             .next()
             .unwrap()
             .into_inner()
-            .map(|modifier| modifier.as_str().trim_end().to_string())
-            .map(|modifier| match modifier.as_str() {
-                "debug" => FunctionMod::Debug,
-                "bar" => FunctionMod::Public,
-                _ => syntax_error(Error::new_from_pos(
-                    pest::error::ErrorVariant::CustomError {
-                        message: "Invalid modifier".to_owned(),
-                    },
-                    start_pos,
-                )),
+            .filter_map(|modifier| match modifier.as_str().trim_end() {
+                "debug" => Some(FunctionMod::Debug),
+                "bar" => Some(FunctionMod::Public),
+                _ => invalid_modifier(modifier, diagnostics),
             })
             .collect();
 
-        let declaration = Declaration::parse_from(inner.next().unwrap()).unwrap();
+        let declaration = Declaration::parse_from(inner.next().unwrap(), diagnostics).unwrap();
 
-        let raw_args = inner.next().unwrap();
-        let start_pos = raw_args.as_span().start_pos();
-        let args: Vec<Declaration> = raw_args
+        let args: Vec<Declaration> = inner
+            .next()
+            .unwrap()
             .into_inner()
-            .map(|x| Declaration::parse_from(x).unwrap())
+            .map(|x| Declaration::parse_from(x, diagnostics).unwrap())
             .collect();
-
-        let has_duplicates = !is_unique(args.iter().map(|x| &x.ident.0));
-        if has_duplicates {
-            syntax_error(Error::new_from_pos(
-                pest::error::ErrorVariant::CustomError {
-                    message: "Duplicate arguments".to_owned(),
-                },
-                start_pos,
-            ))
+        for (i, arg) in args.iter().enumerate() {
+            if let Some(first) = args[..i].iter().find(|x| x.ident.0 == arg.ident.0) {
+                diagnostics.push(
+                    Diagnostic::error(format!("Duplicate argument `{}`", arg.ident.0))
+                        .with_span(arg.ident.1)
+                        .with_secondary(first.ident.1, "First declared here"),
+                );
+            }
         }
-        let body = Tree::parse_from(inner.next().unwrap()).unwrap();
+        let body = Tree::parse_from(inner.next().unwrap(), diagnostics).unwrap();
         Some(Self {
             modifiers,
             declaration,
             args,
             body,
+            span,
         })
     }
 }
 
 impl Parse for Term {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
         match pair.as_rule() {
             Rule::String => Some(Self::String(
                 enquote::unquote(pair.as_str()).unwrap().to_string(),
             )),
-            Rule::UNumber => None,
             Rule::Number => {
                 let mut inner = pair.into_inner();
                 let has_sign = inner.len() == 2;
@@ -101,221 +107,426 @@ impl Parse for Term {
                 let value = if is_negative { -magnitude } else { magnitude };
                 Some(Self::Number(value))
             }
-            Rule::Ident => Some(Self::Ident(Ident::parse_from(pair).unwrap())),
-            Rule::Expr => None, // TODO: Expr in parenthases
+            Rule::Boolean => Some(Self::Boolean(
+                pair.into_inner().next().unwrap().as_rule() == Rule::True,
+            )),
+            Rule::Foolean => {
+                let variant = pair.into_inner().next().unwrap();
+                Some(Self::Foolean(match variant.as_rule() {
+                    Rule::FooleanNull => Foolean::Dunno,
+                    Rule::FooleanIOFalure => Foolean::Huh,
+                    Rule::FooleanRandom => Foolean::Yeet,
+                    _ => bug!("UNKNOWN_FOOLEAN({:?})", variant.as_rule()),
+                }))
+            }
+            Rule::Ident => Some(Self::Ident(Ident::parse_from(pair, diagnostics).unwrap())),
+            Rule::Member => Some(Self::Member(Member::parse_from(pair, diagnostics).unwrap())),
+            Rule::Call => Some(Self::Call(Call::parse_from(pair, diagnostics).unwrap())),
+            Rule::Array => Some(Self::Array(
+                pair.into_inner()
+                    .map(|x| Expr::parse_from(x, diagnostics).unwrap())
+                    .collect(),
+            )),
+            Rule::Map => Some(Self::Map(
+                pair.into_inner()
+                    .map(|entry| {
+                        let mut entry = entry.into_inner();
+                        (
+                            Expr::parse_from(entry.next().unwrap(), diagnostics).unwrap(),
+                            Expr::parse_from(entry.next().unwrap(), diagnostics).unwrap(),
+                        )
+                    })
+                    .collect(),
+            )),
+            Rule::Expr => Some(Self::Expr(Box::new(
+                Expr::parse_from(pair, diagnostics).unwrap(),
+            ))),
             _ => None,
         }
     }
 }
 
 impl Parse for Module {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
-        let ident = Ident::parse_from(inner.next().unwrap()).unwrap();
-        Some(Self { ident })
+        let ident = Ident::parse_from(inner.next().unwrap(), diagnostics).unwrap();
+        Some(Self { ident, span })
     }
 }
 
 impl Parse for Call {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
-        let ident = Ident::parse_from(inner.next().unwrap()).unwrap();
+        let callee = inner.next().unwrap();
+        let (receiver, ident) = match callee.as_rule() {
+            Rule::Ident => (None, Ident::parse_from(callee, diagnostics).unwrap()),
+            // `call a.b.c()` calls `c` on `a.b`
+            Rule::Member => {
+                let Member {
+                    object,
+                    mut members,
+                    ..
+                } = Member::parse_from(callee, diagnostics).unwrap();
+                let ident = members.pop().unwrap();
+                let receiver = match members.last() {
+                    Some(last) => Term::Member(Member {
+                        span: object.1.to(last.1),
+                        object,
+                        members,
+                    }),
+                    None => Term::Ident(object),
+                };
+                (Some(Box::new(receiver)), ident)
+            }
+            _ => bug!("INVALID_CALLEE({:?})", callee.as_rule()),
+        };
+        // `call x` is the same as `call x()`
         let args = inner
             .next()
-            .unwrap()
-            .into_inner()
-            .map(|x| Term::parse_from(x.into_inner().next().unwrap()).unwrap())
+            .map(|x| {
+                x.into_inner()
+                    .map(|x| Expr::parse_from(x, diagnostics).unwrap())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            receiver,
+            ident,
+            args,
+            span,
+        })
+    }
+}
+
+impl Parse for Member {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
+        let mut inner = pair.into_inner();
+        let object = Ident::parse_from(inner.next().unwrap(), diagnostics).unwrap();
+        let members = inner
+            .map(|x| Ident::parse_from(x, diagnostics).unwrap())
             .collect();
-        Some(Self { ident, args })
+        Some(Self {
+            object,
+            members,
+            span,
+        })
     }
 }
 
 impl Parse for Break {
-    fn parse_from(_pair: Pair<'_, Rule>) -> Option<Self> {
-        Some(Break)
+    fn parse_from(pair: Pair<'_, Rule>, _diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        Some(Break {
+            span: pair.as_span().into(),
+        })
     }
 }
 
 impl Parse for Throw {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
-        let value = Expr::parse_from(inner.next().unwrap()).unwrap();
-        Some(Self { value })
+        let value = Expr::parse_from(inner.next().unwrap(), diagnostics).unwrap();
+        Some(Self { value, span })
     }
 }
 
 impl Parse for Import {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
-        let path = Term::parse_from(inner.next().unwrap()).unwrap();
-        Some(Self { path })
+        let path = inner.next().unwrap();
+        let path_span = path.as_span();
+        let path = Term::parse_from(path, diagnostics).unwrap();
+        // The module is referred to by this name
+        if let Term::String(x) = &path {
+            check_ident_length(x, path_span, diagnostics);
+        }
+        Some(Self { path, span })
     }
 }
 
 impl Parse for Loop {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
+        let mut next = inner.next().unwrap();
+        let each = match next.as_rule() {
+            Rule::Each => {
+                let span = next.as_span().into();
+                let mut each = next.into_inner();
+                next = inner.next().unwrap();
+                Some(Each {
+                    ident: Ident::parse_from(each.next().unwrap(), diagnostics).unwrap(),
+                    collection: Expr::parse_from(each.next().unwrap(), diagnostics).unwrap(),
+                    span,
+                })
+            }
+            _ => None,
+        };
+
         Some(Self {
-            body: Tree::parse_from(inner.next().unwrap()).unwrap(),
+            each,
+            body: Tree::parse_from(next, diagnostics).unwrap(),
+            span,
         })
     }
 }
 
 impl Parse for TryCatch {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
 
-        let r#try = Try(Tree::parse_from(inner.next().unwrap()).unwrap());
-        let mut catch = inner.next().unwrap().into_inner();
+        let try_pair = inner.next().unwrap();
+        let try_span = try_pair.as_span().into();
+        let try_block = try_pair.into_inner().next().unwrap();
+        let r#try = Try(Tree::parse_from(try_block, diagnostics).unwrap(), try_span);
 
+        let catch_pair = inner.next().unwrap();
+        let catch_span = catch_pair.as_span().into();
+        let mut catch = catch_pair.into_inner();
         let first = catch.next().unwrap();
         let catch = match first.as_rule() {
-            Rule::Block => Catch(None, Tree::parse_from(first).unwrap()),
+            Rule::Block => Catch(
+                None,
+                Tree::parse_from(first, diagnostics).unwrap(),
+                catch_span,
+            ),
             Rule::Ident => Catch(
-                Ident::parse_from(first),
-                Tree::parse_from(catch.next().unwrap()).unwrap(),
+                Ident::parse_from(first, diagnostics),
+                Tree::parse_from(catch.next().unwrap(), diagnostics).unwrap(),
+                catch_span,
             ),
             _ => bug!("CATCH_NOT_BLOCK_OR_IDENT({:?})", first.as_rule()),
         };
-        Some(TryCatch { r#try, catch })
+        Some(TryCatch { r#try, catch, span })
     }
 }
 
 impl Parse for Variable {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
-        let start_pos = pair.as_span().start_pos();
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
         let modifiers: Vec<VariableMod> = inner
             .next()
             .unwrap()
             .into_inner()
-            .map(|modifier| modifier.as_str().trim_end().to_string())
-            .map(|modifier| match modifier.as_str() {
-                "bar" => VariableMod::Public,
-                _ => syntax_error(Error::new_from_pos(
-                    pest::error::ErrorVariant::CustomError {
-                        message: "Invalid modifier".to_owned(),
-                    },
-                    start_pos,
-                )),
+            .filter_map(|modifier| match modifier.as_str().trim_end() {
+                "bar" => Some(VariableMod::Public),
+                _ => invalid_modifier(modifier, diagnostics),
             })
             .collect();
-        let declaration = Declaration::parse_from(inner.next().unwrap()).unwrap();
-        let value = Expr::parse_from(inner.next().unwrap()).unwrap();
+        let declaration = Declaration::parse_from(inner.next().unwrap(), diagnostics).unwrap();
+        let value = Expr::parse_from(inner.next().unwrap(), diagnostics).unwrap();
 
         Some(Self {
             modifiers,
             declaration,
             value,
+            span,
         })
     }
 }
 
-impl Parse for BinaryExpr {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
-        let mut pairs = pair.into_inner().collect::<Vec<_>>();
-        let first = &[pairs.remove(0)];
-
-        let mut pairs = pairs.chunks(2).collect::<Vec<_>>();
-        pairs.insert(0, first);
-
-        Some(BinaryExpr {
-            terms: pairs
-                .into_iter()
-                .map(|x| {
-                    let operator = if x.len() == 2 { x.get(0) } else { None };
-                    let operator =
-                        operator.map(|x| match x.clone().into_inner().next().unwrap().as_rule() {
-                            Rule::Add => MathOperator::Add,
-                            Rule::Subtract => MathOperator::Subtract,
-                            Rule::Multiply => MathOperator::Multiply,
-                            Rule::Divide => MathOperator::Divide,
-                            Rule::XOR => MathOperator::XOR,
-                            _ => bug!("UNKNOWN_OPERATOR({:?})", x.as_rule()),
-                        });
-                    let operand = Term::parse_from(x.last().unwrap().clone()).unwrap();
-                    BinaryExprTerm { operand, operator }
-                })
-                .collect::<Vec<_>>(),
+impl Parse for Assignment {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
+        let mut inner = pair.into_inner();
+        let target = inner.next().unwrap();
+        let (ident, members) = match target.as_rule() {
+            Rule::Member => {
+                let member = Member::parse_from(target, diagnostics).unwrap();
+                (member.object, member.members)
+            }
+            _ => (Ident::parse_from(target, diagnostics).unwrap(), vec![]),
+        };
+
+        let mut rest: Vec<Pair<'_, Rule>> = inner.collect();
+        let value = Expr::parse_from(rest.pop().unwrap(), diagnostics).unwrap();
+        let indexes = rest.into_iter().map(|x| index(x, diagnostics)).collect();
+        Some(Self {
+            ident,
+            members,
+            indexes,
+            value,
+            span,
         })
     }
 }
 
-impl Parse for ConditionalExpr {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
-        Some(Self {
-            terms: pair
-                .into_inner()
-                .collect::<Vec<_>>()
-                .chunks(2)
-                .map(|x| ConditionExprTerm {
-                    operand: Term::parse_from((x[0]).clone()).unwrap(),
-                    operator: x.get(1).and_then(|x| {
-                        let rule = x.clone().into_inner().next().unwrap().as_rule();
-                        match rule {
-                            Rule::Equality => Some(ConditionalOperator::Equality),
-                            Rule::Inequality => Some(ConditionalOperator::AntiEquality),
-                            _ => bug!("UNKNOWN_COND_OPERATOR({:?})", rule),
-                        }
-                    }),
-                })
-                .collect::<Vec<_>>(),
-        })
+impl Parse for Ident {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        check_ident_length(pair.as_str(), pair.as_span(), diagnostics);
+        Some(Self(pair.as_str().to_string(), pair.as_span().into()))
     }
 }
 
-impl Parse for Assignment {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
-        let mut inner = pair.into_inner();
-        let ident = Ident::parse_from(inner.next().unwrap()).unwrap();
-        let value = Expr::parse_from(inner.next().unwrap()).unwrap();
-        Some(Self { ident, value })
+/// The longest an identifier can be, see the identifier policy
+const MAX_IDENT_LENGTH: usize = 25;
+
+/// Reports an `AntiJavaException` at `span` when `ident` is too long. Also used for names that are
+/// not an [`Ident`], like the module of a `weneed`
+fn check_ident_length(ident: &str, span: pest::Span<'_>, diagnostics: &mut Vec<Diagnostic>) {
+    let length = ident.chars().count();
+    if length > MAX_IDENT_LENGTH {
+        diagnostics.push(
+            Diagnostic::error(format!("`{}` is {} characters long", ident, length))
+                .with_code("AntiJavaException")
+                .with_span(span.into())
+                .with_note(format!(
+                    "Identifiers can have at most {} characters",
+                    MAX_IDENT_LENGTH
+                )),
+        );
     }
 }
 
-impl Parse for Ident {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
-        Some(Self(pair.as_str().to_string()))
+/// Reports a modifier that can not be used on the item, which is parsed without it
+fn invalid_modifier<T>(modifier: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<T> {
+    diagnostics.push(Diagnostic::error("Invalid modifier").with_span(modifier.as_span().into()));
+    None
+}
+
+/// Lowest precedence first
+fn pratt_parser() -> &'static PrattParser<Rule> {
+    static PRATT_PARSER: OnceLock<PrattParser<Rule>> = OnceLock::new();
+    PRATT_PARSER.get_or_init(|| {
+        PrattParser::new()
+            .op(Op::infix(Rule::Equality, Assoc::Left)
+                | Op::infix(Rule::Inequality, Assoc::Left)
+                | Op::infix(Rule::GreaterThan, Assoc::Left)
+                | Op::infix(Rule::GreaterThanOrEqual, Assoc::Left)
+                | Op::infix(Rule::LessThan, Assoc::Left)
+                | Op::infix(Rule::LessThanOrEqual, Assoc::Left))
+            .op(Op::infix(Rule::XOR, Assoc::Left))
+            .op(Op::infix(Rule::Add, Assoc::Left) | Op::infix(Rule::Subtract, Assoc::Left))
+            .op(Op::infix(Rule::Multiply, Assoc::Left) | Op::infix(Rule::Divide, Assoc::Left))
+            .op(Op::prefix(Rule::UnaryOperator))
+    })
+}
+
+fn math_operator(rule: Rule) -> Option<MathOperator> {
+    match rule {
+        Rule::Add => Some(MathOperator::Add),
+        Rule::Subtract => Some(MathOperator::Subtract),
+        Rule::Multiply => Some(MathOperator::Multiply),
+        Rule::Divide => Some(MathOperator::Divide),
+        Rule::XOR => Some(MathOperator::XOR),
+        _ => None,
+    }
+}
+
+fn conditional_operator(rule: Rule) -> Option<ConditionalOperator> {
+    match rule {
+        Rule::Equality => Some(ConditionalOperator::Equality),
+        Rule::Inequality => Some(ConditionalOperator::AntiEquality),
+        Rule::GreaterThan => Some(ConditionalOperator::GreaterThan),
+        Rule::GreaterThanOrEqual => Some(ConditionalOperator::GreaterThanOrEqual),
+        Rule::LessThan => Some(ConditionalOperator::LessThan),
+        Rule::LessThanOrEqual => Some(ConditionalOperator::LessThanOrEqual),
+        _ => None,
     }
 }
 
 impl Parse for Expr {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
-        let start_pos = pair.as_span().start_pos();
-        let value = parse_one(pair).expect("Invalid expression");
-        match value {
-            Node::Expr(x) => Some(x),
-            _ => syntax_error(Error::new_from_pos(
-                pest::error::ErrorVariant::CustomError {
-                    message: "Value is not an expression".to_owned(),
-                },
-                start_pos,
-            )),
-        }
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let expr = pratt_parser()
+            .map_primary(|primary| {
+                let span = primary.as_span().into();
+                match primary.as_rule() {
+                    Rule::IndexExpr => match IndexExpr::parse_from(primary, diagnostics) {
+                        Some(x) => Expr::IndexExpr(x),
+                        // Already reported, the rest is parsed as if it was `wat`
+                        None => Expr::Null(span),
+                    },
+                    Rule::Null => Expr::Null(span),
+                    _ => Expr::Term(Term::parse_from(primary, diagnostics).unwrap(), span),
+                }
+            })
+            .map_prefix(|operator, operand| {
+                let span = Span::from(operator.as_span()).to(operand.span());
+                let operator = operator.into_inner().next().unwrap();
+                let operator = match operator.as_rule() {
+                    Rule::Add => UnaryOperator::Positive,
+                    Rule::Subtract => UnaryOperator::Negative,
+                    Rule::Negation => UnaryOperator::Negation,
+                    _ => bug!("UNKNOWN_UNARY_OPERATOR({:?})", operator.as_rule()),
+                };
+
+                Expr::UnaryExpr(UnaryExpr {
+                    operator,
+                    operand: Box::new(operand),
+                    span,
+                })
+            })
+            .map_infix(|left, operator, right| {
+                let span = left.span().to(right.span());
+                if let Some(operator) = math_operator(operator.as_rule()) {
+                    return Expr::BinaryExpr(BinaryExpr {
+                        left: Box::new(left),
+                        operator,
+                        right: Box::new(right),
+                        span,
+                    });
+                }
+
+                let operator = match conditional_operator(operator.as_rule()) {
+                    Some(x) => x,
+                    None => bug!("UNKNOWN_OPERATOR({:?})", operator.as_rule()),
+                };
+
+                // Chained conditions, `a < b < c`, stay flat. Parenthesised ones are a `Term`
+                let mut terms = match left {
+                    Expr::ConditionalExpr(x) => x.terms,
+                    left => vec![ConditionExprTerm {
+                        span: left.span(),
+                        operand: left,
+                        operator: None,
+                    }],
+                };
+                terms.last_mut().unwrap().operator = Some(operator);
+                terms.push(ConditionExprTerm {
+                    span: right.span(),
+                    operand: right,
+                    operator: None,
+                });
+
+                Expr::ConditionalExpr(ConditionalExpr { terms, span })
+            })
+            .parse(pair.into_inner());
+
+        Some(expr)
     }
 }
 
 impl Parse for Tree {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
-        Some(parse(pair.into_inner()))
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        Some(parse(pair.into_inner(), diagnostics))
     }
 }
 
 impl Parse for IfBlock {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let if_nodes: Vec<IfNode> = pair
             .into_inner()
             .map(|x| match x.as_rule() {
                 Rule::If | Rule::ElseIf | Rule::Else => {
                     let rule = x.as_rule();
+                    let span = x.as_span().into();
                     let mut inner = x.into_inner();
                     match rule {
-                        Rule::If | Rule::ElseIf => IfNode::Case(IfCase {
-                            expr: Expr::parse_from(inner.next().unwrap()).unwrap(),
-                            body: Tree::parse_from(inner.next().unwrap()).unwrap(),
-                        }),
+                        Rule::If | Rule::ElseIf => IfNode::Case(Box::new(IfCase {
+                            expr: Expr::parse_from(inner.next().unwrap(), diagnostics).unwrap(),
+                            body: Tree::parse_from(inner.next().unwrap(), diagnostics).unwrap(),
+                            span,
+                        })),
                         Rule::Else => IfNode::Else(Else {
-                            body: Tree::parse_from(inner.next().unwrap()).unwrap(),
+                            body: Tree::parse_from(inner.next().unwrap(), diagnostics).unwrap(),
+                            span,
                         }),
                         _ => bug!("IMPOSSIBLE_ERROR"),
                     }
@@ -324,40 +535,178 @@ impl Parse for IfBlock {
             })
             .collect();
 
-        Some(Self { if_nodes })
+        Some(Self { if_nodes, span })
     }
 }
 
 impl Parse for Return {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
-        let value = Expr::parse_from(inner.next().unwrap()).unwrap();
-        Some(Self { value })
+        let value = Expr::parse_from(inner.next().unwrap(), diagnostics).unwrap();
+        Some(Self { value, span })
     }
 }
 
 impl Parse for Class {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
 
-        let ident = Ident::parse_from(inner.next().unwrap()).unwrap();
-        let body = Tree::parse_from(inner.next().unwrap()).unwrap();
+        let modifiers: Vec<ClassMod> = inner
+            .next()
+            .unwrap()
+            .into_inner()
+            .filter_map(|modifier| match modifier.as_str().trim_end() {
+                "bar" => Some(ClassMod::Public),
+                _ => invalid_modifier(modifier, diagnostics),
+            })
+            .collect();
+        let ident = Ident::parse_from(inner.next().unwrap(), diagnostics).unwrap();
+        let body = inner.next().unwrap();
+
+        // Fields come first, then methods
+        let mut fields = vec![];
+        let mut methods = vec![];
+        for statement in body.into_inner() {
+            let span = statement.as_span().into();
+            let message = match parse_one(statement, diagnostics) {
+                Some(Node::Variable(x)) if methods.is_empty() => {
+                    fields.push(x);
+                    continue;
+                }
+                Some(Node::Function(x)) => {
+                    methods.push(x);
+                    continue;
+                }
+                Some(Node::Variable(_)) => "Fields must be placed before methods",
+                _ => "Only fields and methods can be placed in a `school`",
+            };
+            diagnostics.push(Diagnostic::error(message).with_span(span));
+        }
 
-        Some(Self { ident, body })
+        Some(Self {
+            modifiers,
+            ident,
+            fields,
+            methods,
+            span,
+        })
     }
 }
 
+/// The expression inside of an `Index`
+fn index(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Expr {
+    Expr::parse_from(pair.into_inner().next().unwrap(), diagnostics).unwrap()
+}
+
 impl Parse for IndexExpr {
-    fn parse_from(pair: Pair<'_, Rule>) -> Option<Self> {
+    fn parse_from(pair: Pair<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Option<Self> {
+        let span = pair.as_span().into();
         let mut inner = pair.into_inner();
 
-        let term = Term::parse_from(inner.next().unwrap()).unwrap();
-        let index = Term::parse_from(inner.next().unwrap()).unwrap();
-        let index = match index {
-            Term::Number(x) => Index::Number(x),
-            Term::String(x) => Index::String(x),
-            _ => bug!("INVALID_INDEX_TERM({:?})", index),
-        };
-        Some(Self { term, index })
+        // `wat` is a literal, so the grammar lets it be indexed
+        let term = inner.next().unwrap();
+        if term.as_rule() == Rule::Null {
+            diagnostics.push(Diagnostic::error("`wat` can not be indexed").with_span(span));
+            return None;
+        }
+
+        let term = Term::parse_from(term, diagnostics).unwrap();
+        let indexes = inner.map(|x| index(x, diagnostics)).collect();
+        Some(Self {
+            term,
+            indexes,
+            span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pest::Parser;
+
+    use super::*;
+    use crate::RLParser;
+
+    /// Parses `source` as an expression and writes it back with every operation in parentheses
+    fn grouped(source: &str) -> String {
+        let pair = RLParser::parse(Rule::Expr, source).unwrap().next().unwrap();
+        show(&Expr::parse_from(pair, &mut vec![]).unwrap())
+    }
+
+    fn show(expr: &Expr) -> String {
+        match expr {
+            Expr::UnaryExpr(x) => format!("({:?} {})", x.operator, show(&x.operand)),
+            Expr::BinaryExpr(x) => {
+                format!("({} {:?} {})", show(&x.left), x.operator, show(&x.right))
+            }
+            Expr::ConditionalExpr(x) => {
+                let terms: Vec<String> = x
+                    .terms
+                    .iter()
+                    .map(|x| match &x.operator {
+                        Some(operator) => format!("{} {:?}", show(&x.operand), operator),
+                        None => show(&x.operand),
+                    })
+                    .collect();
+                format!("({})", terms.join(" "))
+            }
+            Expr::Term(Term::Number(x), _) => x.to_string(),
+            Expr::Term(Term::Ident(x), _) => x.0.to_string(),
+            Expr::Term(Term::Expr(x), _) => show(x),
+            x => format!("{:?}", x),
+        }
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(grouped("1 ⨋ 2 ⋇ 3"), "(1 Add (2 Multiply 3))");
+        assert_eq!(
+            grouped("1 ⋇ 2 – 3 ⎲ 4"),
+            "((1 Multiply 2) Subtract (3 Divide 4))"
+        );
+    }
+
+    #[test]
+    fn operators_of_the_same_precedence_are_left_associative() {
+        assert_eq!(grouped("1 – 2 – 3"), "((1 Subtract 2) Subtract 3)");
+        assert_eq!(grouped("8 ⎲ 4 ⋇ 2"), "((8 Divide 4) Multiply 2)");
+    }
+
+    #[test]
+    fn xor_binds_looser_than_math() {
+        assert_eq!(grouped("1 ⊕ 2 ⨋ 3"), "(1 XOR (2 Add 3))");
+    }
+
+    #[test]
+    fn comparisons_bind_loosest_and_chain() {
+        assert_eq!(grouped("1 ⨋ 2 < 4"), "((1 Add 2) LessThan 4)");
+        assert_eq!(grouped("a < b ⋜ c"), "(a LessThan b LessThanOrEqual c)");
+    }
+
+    #[test]
+    fn parentheses_nest_to_any_depth() {
+        assert_eq!(grouped("(1 ⨋ 2) ⋇ 3"), "((1 Add 2) Multiply 3)");
+        assert_eq!(grouped("((((1 ⨋ 2))))"), "(1 Add 2)");
+        assert_eq!(
+            grouped("2 ⋇ (3 ⋇ (4 – (5 ⨋ 6)))"),
+            "(2 Multiply (3 Multiply (4 Subtract (5 Add 6))))"
+        );
+        assert_eq!(
+            grouped("(1 < 2) ⅀ (3 > 4)"),
+            "((1 LessThan 2) Equality (3 GreaterThan 4))"
+        );
+    }
+
+    #[test]
+    fn unary_operators_bind_tightest() {
+        assert_eq!(
+            grouped("– (1 ⨋ 2) ⋇ 3"),
+            "((Negative (1 Add 2)) Multiply 3)"
+        );
+        assert_eq!(grouped("1 – – 2"), "(1 Subtract (Negative 2))");
+        assert_eq!(grouped("¡a ⅀ ⨋ b"), "((Negation a) Equality (Positive b))");
+        assert_eq!(grouped("¡¡a"), "(Negation (Negation a))");
     }
 }
@@ -1,38 +1,120 @@
-use crate::Rule;
+use std::rc::Rc;
+
+use pest::Parser;
+
+use crate::{
+    errors::{catch_bug, syntax_error, Diagnostic},
+    modules::SourceFile,
+    RLParser, Rule,
+};
 
 use self::from_pair::Parse;
 
 pub mod from_pair;
 pub type Number = f64; // Number type
 
+/// Where a node is in its file, from its first character to the one after its last
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+/// A byte offset into a file, with the line and column it is on. Both count from 1
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Location {
+    pub offset: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl From<pest::Position<'_>> for Location {
+    fn from(position: pest::Position<'_>) -> Self {
+        let (line, col) = position.line_col();
+        Self {
+            offset: position.pos(),
+            line,
+            col,
+        }
+    }
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        Self {
+            start: span.start_pos().into(),
+            end: span.end_pos().into(),
+        }
+    }
+}
+
+impl Span {
+    /// From the start of `self` to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Term {
     Number(Number),
     String(String),
+    Boolean(bool),
+    Foolean(Foolean),
     Ident(Ident),
+    Member(Member),
+    Call(Call),
+    Array(Vec<Expr>),
+    /// Keys and values
+    Map(Vec<(Expr, Expr)>),
+    Expr(Box<Expr>),
+}
+
+/// The variants a `Foolean` has on top of a `Boolean`
+#[derive(Debug)]
+pub enum Foolean {
+    Dunno,
+    Huh,
+    Yeet,
 }
 
 #[derive(Debug)]
 pub struct Type {
     pub ident: Ident,
     pub is_array: bool,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Declaration {
     pub ident: Ident,
     pub r#type: Option<Type>,
+    pub span: Span,
 }
 
 // Statements
 
 #[derive(Debug)]
 pub struct Loop {
+    pub each: Option<Each>,
     pub body: Tree,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub struct Break;
+pub struct Each {
+    pub ident: Ident,
+    pub collection: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Break {
+    pub span: Span,
+}
 
 #[derive(Debug)]
 pub struct Function {
@@ -40,6 +122,7 @@ pub struct Function {
     pub declaration: Declaration,
     pub args: Vec<Declaration>,
     pub body: Tree,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -50,41 +133,57 @@ pub enum FunctionMod {
 
 #[derive(Debug)]
 pub struct Call {
+    /// The object a method is called on, `None` for functions and constructors
+    pub receiver: Option<Box<Term>>,
     pub ident: Ident,
-    pub args: Vec<Term>,
+    pub args: Vec<Expr>,
+    pub span: Span,
+}
+
+/// `object.members[0].members[1]...`
+#[derive(Debug)]
+pub struct Member {
+    pub object: Ident,
+    pub members: Vec<Ident>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Throw {
     pub value: Expr,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Import {
     pub path: Term,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Module {
     pub ident: Ident,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct TryCatch {
     pub r#try: Try,
     pub catch: Catch,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub struct Try(pub Tree);
+pub struct Try(pub Tree, pub Span);
 #[derive(Debug)]
-pub struct Catch(pub Option<Ident>, pub Tree);
+pub struct Catch(pub Option<Ident>, pub Tree, pub Span);
 
 #[derive(Debug)]
 pub struct Variable {
     pub modifiers: Vec<VariableMod>,
     pub declaration: Declaration,
     pub value: Expr,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -95,17 +194,23 @@ pub enum VariableMod {
 #[derive(Debug)]
 pub struct Assignment {
     pub ident: Ident,
+    /// Fields of `ident` that are assigned to, empty when assigning the variable itself
+    pub members: Vec<Ident>,
+    /// Indexes after `members`, the last one is assigned to
+    pub indexes: Vec<Expr>,
     pub value: Expr,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct IfBlock {
     pub if_nodes: Vec<IfNode>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub enum IfNode {
-    Case(IfCase),
+    Case(Box<IfCase>),
     Else(Else),
 }
 
@@ -113,22 +218,33 @@ pub enum IfNode {
 pub struct IfCase {
     pub body: Tree,
     pub expr: Expr,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Else {
     pub body: Tree,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Class {
+    pub modifiers: Vec<ClassMod>,
     pub ident: Ident,
-    pub body: Tree,
+    pub fields: Vec<Variable>,
+    pub methods: Vec<Function>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum ClassMod {
+    Public,
 }
 
 #[derive(Debug)]
 pub struct Return {
     pub value: Expr,
+    pub span: Span,
 }
 
 // Operators
@@ -141,10 +257,21 @@ pub enum MathOperator {
     XOR,
 }
 
+#[derive(Debug)]
+pub enum UnaryOperator {
+    Positive,
+    Negative,
+    Negation,
+}
+
 #[derive(Debug)]
 pub enum ConditionalOperator {
     Equality,
     AntiEquality,
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
 }
 
 // Expressions
@@ -152,44 +279,55 @@ pub enum ConditionalOperator {
 pub type ConditionalExpr = ChainedExpr<ConditionalOperator>;
 pub type ConditionExprTerm = ChainedExprTerm<ConditionalOperator>;
 
-pub type BinaryExpr = ChainedExpr<MathOperator>;
-pub type BinaryExprTerm = ChainedExprTerm<MathOperator>;
+#[derive(Debug)]
+pub struct BinaryExpr {
+    pub left: Box<Expr>,
+    pub operator: MathOperator,
+    pub right: Box<Expr>,
+    pub span: Span,
+}
 
 #[derive(Debug)]
-pub struct IndexExpr {
-    pub term: Term,
-    pub index: Index,
+pub struct UnaryExpr {
+    pub operator: UnaryOperator,
+    pub operand: Box<Expr>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub enum Index {
-    Number(Number),
-    String(String),
+pub struct IndexExpr {
+    pub term: Term,
+    /// `x[1][2]` has two, applied from left to right
+    pub indexes: Vec<Expr>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct ChainedExpr<T> {
     pub terms: Vec<ChainedExprTerm<T>>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct ChainedExprTerm<T> {
-    pub operand: Term,
+    pub operand: Expr,
 
     /// None on the last term
     pub operator: Option<T>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
-pub struct Ident(pub String);
+pub struct Ident(pub String, pub Span);
 
 #[derive(Debug)]
 pub enum Expr {
+    UnaryExpr(UnaryExpr),
     BinaryExpr(BinaryExpr),
     ConditionalExpr(ConditionalExpr),
     IndexExpr(IndexExpr),
-    Term(Term),
-    Null,
+    Term(Term, Span),
+    Null(Span),
 }
 
 // AST
@@ -213,56 +351,149 @@ pub enum Node {
 
 pub type Tree = Vec<Node>;
 
-pub fn parse_one(pair: pest::iterators::Pair<'_, Rule>) -> Option<Node> {
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::UnaryExpr(x) => x.span,
+            Expr::BinaryExpr(x) => x.span,
+            Expr::ConditionalExpr(x) => x.span,
+            Expr::IndexExpr(x) => x.span,
+            Expr::Term(_, x) | Expr::Null(x) => *x,
+        }
+    }
+}
+
+impl Node {
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Loop(x) => x.span,
+            Node::Break(x) => x.span,
+            Node::Function(x) => x.span,
+            Node::Call(x) => x.span,
+            Node::Throw(x) => x.span,
+            Node::Import(x) => x.span,
+            Node::Module(x) => x.span,
+            Node::TryCatch(x) => x.span,
+            Node::Variable(x) => x.span,
+            Node::Assignment(x) => x.span,
+            Node::If(x) => x.span,
+            Node::Class(x) => x.span,
+            Node::Return(x) => x.span,
+            Node::Expr(x) => x.span(),
+        }
+    }
+}
+
+/// Problems that do not stop parsing, like a misplaced statement, are added to `diagnostics`
+pub fn parse_one(
+    pair: pest::iterators::Pair<'_, Rule>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Node> {
     match pair.as_rule() {
         Rule::Statement => {
             let statement = pair.into_inner().next().unwrap();
             match statement.as_rule() {
-                Rule::Loop => Some(Node::Loop(Loop::parse_from(statement).unwrap())),
-                Rule::Function => Some(Node::Function(Function::parse_from(statement).unwrap())),
-                Rule::Call => Some(Node::Call(Call::parse_from(statement).unwrap())),
-                Rule::Break => Some(Node::Break(Break::parse_from(statement).unwrap())),
-                Rule::Throw => Some(Node::Throw(Throw::parse_from(statement).unwrap())),
-                Rule::Import => Some(Node::Import(Import::parse_from(statement).unwrap())),
-                Rule::Module => Some(Node::Module(Module::parse_from(statement).unwrap())),
-                Rule::TryCatch => Some(Node::TryCatch(TryCatch::parse_from(statement).unwrap())),
-                Rule::Variable => Some(Node::Variable(Variable::parse_from(statement).unwrap())),
-                Rule::AssignmentStatement => {
-                    Some(Node::Assignment(Assignment::parse_from(statement).unwrap()))
-                }
-                Rule::IfBlock => Some(Node::If(IfBlock::parse_from(statement).unwrap())),
-                Rule::Class => Some(Node::Class(Class::parse_from(statement).unwrap())),
-                Rule::Return => Some(Node::Return(Return::parse_from(statement).unwrap())),
+                Rule::Loop => Some(Node::Loop(
+                    Loop::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Function => Some(Node::Function(
+                    Function::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Call => Some(Node::Call(
+                    Call::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Break => Some(Node::Break(
+                    Break::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Throw => Some(Node::Throw(
+                    Throw::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Import => Some(Node::Import(
+                    Import::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Module => Some(Node::Module(
+                    Module::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::TryCatch => Some(Node::TryCatch(
+                    TryCatch::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Variable => Some(Node::Variable(
+                    Variable::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::AssignmentStatement => Some(Node::Assignment(
+                    Assignment::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::IfBlock => Some(Node::If(
+                    IfBlock::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Class => Some(Node::Class(
+                    Class::parse_from(statement, diagnostics).unwrap(),
+                )),
+                Rule::Return => Some(Node::Return(
+                    Return::parse_from(statement, diagnostics).unwrap(),
+                )),
                 _ => None,
             }
         }
-        Rule::Expr => {
-            let expression = pair.into_inner().next().unwrap();
-            match expression.as_rule() {
-                Rule::BinaryExpr => Some(Node::Expr(Expr::BinaryExpr(
-                    BinaryExpr::parse_from(expression).unwrap(),
-                ))),
-                Rule::ConditionalExpr => Some(Node::Expr(Expr::ConditionalExpr(
-                    ConditionalExpr::parse_from(expression).unwrap(),
-                ))),
-                Rule::IndexExpr => Some(Node::Expr(Expr::IndexExpr(
-                    IndexExpr::parse_from(expression).unwrap(),
-                ))),
-                Rule::Null => Some(Node::Expr(Expr::Null)),
-                _ => Term::parse_from(expression).map(|x| Node::Expr(Expr::Term(x))),
-            }
-        }
+        Rule::Expr => Expr::parse_from(pair, diagnostics).map(Node::Expr),
         _ => None,
     }
 }
 
-pub fn parse(pairs: pest::iterators::Pairs<'_, Rule>) -> Tree {
+pub fn parse(pairs: pest::iterators::Pairs<'_, Rule>, diagnostics: &mut Vec<Diagnostic>) -> Tree {
+    parse_tree(pairs, false, diagnostics)
+}
+
+/// Parses `file`. A syntax error stops it from being parsed, other problems are all collected and
+/// returned with the tree
+pub fn parse_file(file: &Rc<SourceFile>) -> Result<(Tree, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let pairs =
+        RLParser::parse(Rule::Program, &file.code).map_err(|x| vec![syntax_error(x, file)])?;
+    match catch_bug(|| parse_program(pairs)) {
+        Ok((tree, diagnostics)) => Ok((
+            tree,
+            diagnostics.into_iter().map(|x| x.in_file(file)).collect(),
+        )),
+        Err(x) => Err(vec![x.in_file(file)]),
+    }
+}
+
+/// The `subreddit` line at the start of `file`, without parsing the rest of it. Its problems are
+/// found when the whole file is parsed
+pub fn parse_header(file: &SourceFile) -> Option<Module> {
+    let pair = RLParser::parse(Rule::Header, &file.code).ok()?.next()?;
+    catch_bug(|| Module::parse_from(pair, &mut vec![])).ok()?
+}
+
+/// Parses a whole file, which unlike a block can have `subreddit` and `weneed`. Returns what could
+/// be parsed with all of its problems
+pub fn parse_program(pairs: pest::iterators::Pairs<'_, Rule>) -> (Tree, Vec<Diagnostic>) {
+    let mut diagnostics = vec![];
+    let tree = parse_tree(pairs, true, &mut diagnostics);
+    (tree, diagnostics)
+}
+
+fn parse_tree(
+    pairs: pest::iterators::Pairs<'_, Rule>,
+    top_level: bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Tree {
     let mut tree: Tree = vec![];
 
     for pair in pairs {
-        let node = parse_one(pair);
-        if node.is_some() {
-            tree.push(node.unwrap());
+        if let Some(node) = parse_one(pair, diagnostics) {
+            let message = match node {
+                Node::Module(_) if !top_level || !tree.is_empty() => {
+                    Some("`subreddit` can only be at the top of a file")
+                }
+                Node::Import(_) if !top_level => Some("`weneed` can not be used inside of a block"),
+                _ => None,
+            };
+            if let Some(message) = message {
+                diagnostics.push(Diagnostic::error(message).with_span(node.span()));
+            }
+
+            tree.push(node);
         }
     }
     tree
@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    compiler::{
+        class::{method_function_name, CONSTRUCTOR, DESTRUCTOR},
+        linking::libstd_functions,
+        namespace::Namespace,
+    },
+    errors::Diagnostic,
+    modules::SourceModule,
+    parser::{self, Class, ClassMod, Function, FunctionMod, Ident, Node, Span, Tree, VariableMod},
+};
+
+use self::{
+    node::leaves,
+    types::{ClassInfo, Field, Signature, Type, Variable},
+};
+
+pub mod node;
+pub mod types;
+
+/// Resolves the names of a program and checks how they are used, so the compiler only sees valid
+/// programs. Modules are checked in the order they are compiled, each one can use what the
+/// modules it imports export
+pub struct Sema {
+    /// Signatures of every function by the name it is compiled as, including constructors and
+    /// methods
+    pub functions: HashMap<String, Signature>,
+    /// Fields and methods of every class, by name
+    pub classes: HashMap<String, ClassInfo>,
+    /// Functions and classes that were declared again. Uses of them are not checked, they could
+    /// be meant for the declaration that was reported
+    pub redeclared: HashSet<String>,
+    /// What the module being checked can refer to
+    pub namespace: Namespace,
+    /// Found so far in the module being checked
+    pub problems: Vec<Diagnostic>,
+    /// How many variables were declared so far, gives each one an id
+    pub variable_count: usize,
+    /// What every `meth` declares its variable as, see [`Declarations::variables`]
+    pub variables: HashMap<(Option<String>, usize), Type>,
+}
+
+/// What a statement can see, like [`crate::compiler::CompileMetadata`] for the compiler
+#[derive(Clone, Default)]
+pub struct Scope {
+    pub variables: HashMap<String, Variable>,
+    /// The function whose body is checked, `None` at the top level of a file
+    pub function: Option<String>,
+    /// How many loops in the current function are around the statement
+    pub loops: usize,
+    /// The class whose method or field is checked, its private members can be used
+    pub class: Option<String>,
+}
+
+/// The functions and classes of a checked program, the compiler lays them out from these instead
+/// of resolving the declarations again
+pub struct Declarations {
+    /// Signatures of every function by the name it is compiled as, including the standard library
+    pub functions: HashMap<String, Signature>,
+    /// Fields and methods of every class, by name
+    pub classes: HashMap<String, ClassInfo>,
+    /// What every `meth` declares its variable as, by its module (`None` for `main.rl`) and
+    /// where its name starts
+    pub variables: HashMap<(Option<String>, usize), Type>,
+}
+
+/// Checks every module, `main.rl` last. Returns what the program declares with the warnings, or
+/// every problem if any of them is an error
+pub fn check(modules: &[SourceModule]) -> Result<(Declarations, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut sema = Sema::new();
+    let diagnostics: Vec<Diagnostic> = modules
+        .iter()
+        .flat_map(|module| {
+            sema.check_module(module)
+                .into_iter()
+                .map(|x| x.in_file(&module.file))
+        })
+        .collect();
+
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return Err(diagnostics);
+    }
+
+    let declarations = Declarations {
+        functions: sema.functions,
+        classes: sema.classes,
+        variables: sema.variables,
+    };
+    Ok((declarations, diagnostics))
+}
+
+impl Default for Sema {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sema {
+    pub fn new() -> Self {
+        Self {
+            functions: libstd_functions()
+                .into_iter()
+                .map(|(name, signature)| (name.to_string(), signature))
+                .collect(),
+            classes: HashMap::new(),
+            redeclared: HashSet::new(),
+            namespace: Namespace::default(),
+            problems: vec![],
+            variable_count: 0,
+            variables: HashMap::new(),
+        }
+    }
+
+    /// Checks a module whose imports were already checked, returning its problems
+    pub fn check_module(&mut self, module: &SourceModule) -> Vec<Diagnostic> {
+        if let Err(x) = self
+            .namespace
+            .enter(module.name.as_deref(), &module.imports)
+        {
+            // Points at the first `weneed`, one of the imports that clash
+            let span = module
+                .tree
+                .iter()
+                .find(|x| matches!(x, Node::Import(_)))
+                .map(Node::span)
+                .unwrap_or_default();
+            self.problem(span, x);
+        }
+
+        self.check_tree(&module.tree, &mut Scope::default());
+        std::mem::take(&mut self.problems)
+    }
+
+    pub fn problem(&mut self, span: Span, message: String) {
+        self.problems
+            .push(Diagnostic::error(message).with_span(span));
+    }
+
+    /// Checks a block. Its classes and functions are declared first, like the compiler does, so
+    /// they can be used before they are defined
+    pub fn check_tree(&mut self, tree: &Tree, scope: &mut Scope) {
+        let classes: Vec<(&Class, String)> = tree
+            .iter()
+            .filter_map(|node| match node {
+                Node::Class(class) => Some((class, self.declare_class_type(class)?)),
+                _ => None,
+            })
+            .collect();
+        for (class, name) in &classes {
+            self.declare_class(class, name);
+        }
+        let functions: Vec<(&Function, String)> = tree
+            .iter()
+            .filter_map(|node| match node {
+                Node::Function(function) => Some((function, self.declare_function(function)?)),
+                _ => None,
+            })
+            .collect();
+
+        // Only the first statement after a `spez`, `shoot` or `sthu` is reported. Classes and
+        // functions are declared before the block runs, so they are not unreachable
+        if let Some(i) = tree
+            .iter()
+            .position(|x| leaves(std::slice::from_ref(x), scope.loops > 0))
+        {
+            let unreachable = tree[i + 1..]
+                .iter()
+                .find(|x| !matches!(x, Node::Class(_) | Node::Function(_)));
+            if let Some(node) = unreachable {
+                self.problems
+                    .push(Diagnostic::warning("This code is never run").with_span(node.span()));
+            }
+        }
+
+        for node in tree {
+            match node {
+                // Items that could not be declared are not checked, their names belong to others
+                Node::Class(class) => {
+                    if let Some((_, name)) = classes.iter().find(|x| std::ptr::eq(x.0, class)) {
+                        self.check_class(class, name);
+                    }
+                }
+                Node::Function(function) => {
+                    if let Some((_, name)) = functions.iter().find(|x| std::ptr::eq(x.0, function))
+                    {
+                        self.check_function(function, name, None);
+                    }
+                }
+                node => self.check_node(node, scope),
+            }
+        }
+    }
+
+    /// Adds a variable to `scope`, shadowing any previous one with the same name
+    pub fn declare(&mut self, scope: &mut Scope, ident: &str, declared: Type, holds: Type) {
+        self.variable_count += 1;
+        scope.variables.insert(
+            ident.to_string(),
+            Variable {
+                id: self.variable_count,
+                declared,
+                holds,
+            },
+        );
+    }
+
+    /// Adds `ident` to the namespace, returning the name it is compiled as
+    fn define(&mut self, ident: &Ident, public: bool) -> Option<String> {
+        match self.namespace.define(&ident.0, public) {
+            Ok(x) => Some(x),
+            Err(x) => {
+                self.problem(ident.1, x);
+                self.redeclared.insert(self.namespace.resolve(&ident.0));
+                None
+            }
+        }
+    }
+
+    /// Resolves a `damn` annotation, no annotation means the value is dynamically typed. Unknown
+    /// types are reported and then dynamically typed too
+    pub fn type_of(&mut self, annotation: Option<&parser::Type>) -> Type {
+        let annotation = match annotation {
+            Some(x) => x,
+            None => return Type::Any,
+        };
+
+        let r#type = match annotation.ident.0.as_str() {
+            "Number" => Type::Number,
+            "Boolean" => Type::Boolean,
+            "Foolean" => Type::Foolean,
+            "String" => Type::String,
+            "Map" => Type::Map,
+            x => {
+                let class = self.namespace.resolve(x);
+                if self.classes.contains_key(&class) {
+                    Type::Object(class)
+                } else {
+                    self.problem(annotation.ident.1, format!("Unknown type `{}`", x));
+                    Type::Any
+                }
+            }
+        };
+
+        if annotation.is_array {
+            Type::Array(Box::new(r#type))
+        } else {
+            r#type
+        }
+    }
+
+    fn declare_class_type(&mut self, class: &Class) -> Option<String> {
+        let public = class
+            .modifiers
+            .iter()
+            .any(|x| matches!(x, ClassMod::Public));
+        let name = self.define(&class.ident, public)?;
+        self.classes.insert(name.to_string(), ClassInfo::default());
+        Some(name)
+    }
+
+    /// Resolves the fields of `class` and the signatures of its constructor and methods
+    fn declare_class(&mut self, class: &Class, name: &str) {
+        let object_type = Type::Object(name.to_string());
+        let mut info = ClassInfo::default();
+
+        for (i, field) in class.fields.iter().enumerate() {
+            let field_ident = &field.declaration.ident;
+            let first = class.fields[..i]
+                .iter()
+                .map(|x| &x.declaration.ident)
+                .find(|x| x.0 == field_ident.0);
+            if let Some(first) = first {
+                self.problems.push(
+                    Diagnostic::error(format!(
+                        "Class `{}` has more than one `{}`",
+                        name, field_ident.0
+                    ))
+                    .with_span(field_ident.1)
+                    .with_secondary(first.1, "First declared here"),
+                );
+            }
+
+            // Duplicates are kept, so the fields line up with their initializers
+            info.fields.push(Field {
+                ident: field_ident.0.to_string(),
+                r#type: self.type_of(field.declaration.r#type.as_ref()),
+                public: field
+                    .modifiers
+                    .iter()
+                    .any(|x| matches!(x, VariableMod::Public)),
+            });
+        }
+
+        let mut constructor_params = vec![];
+        for method in &class.methods {
+            let method_ident = &method.declaration.ident;
+            if info.methods.contains_key(&method_ident.0)
+                || info.fields.iter().any(|x| x.ident == method_ident.0)
+            {
+                self.problem(
+                    method_ident.1,
+                    format!("Class `{}` has more than one `{}`", name, method_ident.0),
+                );
+            }
+
+            let params: Vec<Type> = method
+                .args
+                .iter()
+                .map(|x| self.type_of(x.r#type.as_ref()))
+                .collect();
+            match method_ident.0.as_str() {
+                CONSTRUCTOR => constructor_params = params.clone(),
+                DESTRUCTOR if !params.is_empty() => self.problem(
+                    method_ident.1,
+                    format!("`{}` of `{}` can not take arguments", DESTRUCTOR, name),
+                ),
+                _ => {}
+            }
+
+            // The object is passed as `me`
+            let signature = Signature {
+                params: [object_type.clone()].into_iter().chain(params).collect(),
+                r#return: self.type_of(method.declaration.r#type.as_ref()),
+                foreign: false,
+            };
+            self.functions
+                .insert(method_function_name(name, &method_ident.0), signature);
+
+            let public = method_ident.0 == CONSTRUCTOR
+                || method_ident.0 == DESTRUCTOR
+                || method
+                    .modifiers
+                    .iter()
+                    .any(|x| matches!(x, FunctionMod::Public));
+            info.methods.insert(method_ident.0.to_string(), public);
+        }
+
+        self.functions.insert(
+            name.to_string(),
+            Signature {
+                params: constructor_params,
+                r#return: object_type,
+                foreign: false,
+            },
+        );
+        self.classes.insert(name.to_string(), info);
+    }
+
+    fn declare_function(&mut self, function: &Function) -> Option<String> {
+        let public = function
+            .modifiers
+            .iter()
+            .any(|x| matches!(x, FunctionMod::Public));
+        let ident = &function.declaration.ident;
+        let name = self.define(ident, public)?;
+        if self.functions.contains_key(&name) {
+            self.problem(ident.1, format!("Function `{}` is already defined", name));
+            self.redeclared.insert(name);
+            return None;
+        }
+
+        let signature = Signature {
+            params: function
+                .args
+                .iter()
+                .map(|x| self.type_of(x.r#type.as_ref()))
+                .collect(),
+            r#return: self.type_of(function.declaration.r#type.as_ref()),
+            foreign: false,
+        };
+        self.functions.insert(name.to_string(), signature);
+        Some(name)
+    }
+}
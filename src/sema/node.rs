@@ -0,0 +1,902 @@
+use crate::{
+    compiler::class::method_function_name,
+    errors::Diagnostic,
+    parser::{
+        Assignment, Call, Class, ConditionalOperator, Expr, Function, Ident, IfNode, MathOperator,
+        Node, Span, Term, Tree, UnaryOperator,
+    },
+};
+
+use super::{
+    types::{assigned, Type},
+    Scope, Sema,
+};
+
+impl Sema {
+    pub fn check_node(&mut self, node: &Node, scope: &mut Scope) {
+        match node {
+            Node::Loop(r#loop) => {
+                let collection = r#loop
+                    .each
+                    .as_ref()
+                    .map(|each| self.check_expr(&each.collection, scope));
+
+                // What the body assigns is there the next time around, and after the loop
+                forget(scope, &assigned(&r#loop.body));
+                let mut body_scope = scope.clone();
+                body_scope.loops += 1;
+                if let (Some(each), Some(collection)) = (&r#loop.each, collection) {
+                    if !matches!(collection, Type::Array(_) | Type::Map | Type::Any) {
+                        self.problem(
+                            each.collection.span(),
+                            format!("Can not loop over a `{}`", collection),
+                        );
+                    }
+                    self.declare(&mut body_scope, &each.ident.0, Type::Any, collection.item());
+                }
+                self.check_tree(&r#loop.body, &mut body_scope);
+            }
+            Node::Break(r#break) => {
+                if scope.loops == 0 {
+                    self.problem(
+                        r#break.span,
+                        "`sthu` can only be used inside of a loop".to_string(),
+                    );
+                }
+            }
+            Node::Call(call) => {
+                self.check_call(call, scope);
+            }
+            Node::Throw(throw) => {
+                self.check_expr(&throw.value, scope);
+            }
+            Node::TryCatch(try_catch) => {
+                let mut try_scope = scope.clone();
+                self.check_tree(&try_catch.r#try.0, &mut try_scope);
+
+                // A bullet can hit the wall before or after any assignment of the `test` block
+                forget(scope, &assigned(&try_catch.r#try.0));
+                let mut catch_scope = scope.clone();
+                if let Some(ident) = &try_catch.catch.0 {
+                    self.declare(&mut catch_scope, &ident.0, Type::Any, Type::Any);
+                }
+                self.check_tree(&try_catch.catch.1, &mut catch_scope);
+                merge(scope, &[try_scope, catch_scope]);
+            }
+            Node::Variable(var) => {
+                let ident = &var.declaration.ident.0;
+                let declared = self.type_of(var.declaration.r#type.as_ref());
+                let value = self.check_expr(&var.value, scope);
+                if !declared.accepts(&value) {
+                    self.problem(
+                        var.value.span(),
+                        format!(
+                            "Variable `{}` is declared as a `{}` but got a `{}`",
+                            ident, declared, value
+                        ),
+                    );
+                }
+                self.variables.insert(
+                    (
+                        self.namespace.module.clone(),
+                        var.declaration.ident.1.start.offset,
+                    ),
+                    declared.clone(),
+                );
+                let holds = declared.holding(value);
+                self.declare(scope, ident, declared, holds);
+            }
+            Node::Assignment(assignment) => self.check_assignment(assignment, scope),
+            Node::If(r#if) => {
+                let mut branches = vec![];
+                for if_node in &r#if.if_nodes {
+                    let mut body_scope = scope.clone();
+                    match if_node {
+                        IfNode::Case(x) => {
+                            self.check_expr(&x.expr, scope);
+                            self.check_tree(&x.body, &mut body_scope);
+                        }
+                        IfNode::Else(x) => self.check_tree(&x.body, &mut body_scope),
+                    }
+                    branches.push(body_scope);
+                }
+
+                // Without `isn't` no case might run
+                if !matches!(r#if.if_nodes.last(), Some(IfNode::Else(_))) {
+                    branches.push(scope.clone());
+                }
+                merge(scope, &branches);
+            }
+            Node::Return(r#return) => {
+                let value = self.check_expr(&r#return.value, scope);
+                let span = r#return.value.span();
+                match &scope.function {
+                    Some(function) => {
+                        let kind = self.functions[function].r#return.clone();
+                        if !kind.accepts(&value) {
+                            self.problem(
+                                span,
+                                format!(
+                                    "Function `{}` must `spez` a `{}` but got a `{}`",
+                                    function, kind, value
+                                ),
+                            );
+                        }
+                    }
+                    // Top level, `spez` exits the program with a status code
+                    None if self.namespace.module.is_some() => self.problem(
+                        r#return.span,
+                        "`spez` can only be used in functions and `main.rl`".to_string(),
+                    ),
+                    None => {
+                        if !matches!(value, Type::Number | Type::Any) {
+                            self.problem(
+                                span,
+                                format!(
+                                    "`spez` in `main.rl` needs a `Number` but got a `{}`",
+                                    value
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+            // Declared and checked by `check_tree`, modules are loaded before the file that
+            // imports them
+            Node::Function(_) | Node::Class(_) | Node::Import(_) | Node::Module(_) => {}
+            Node::Expr(x) => {
+                self.check_expr(x, scope);
+            }
+        }
+    }
+
+    /// Checks the field initializers and methods of a declared class
+    pub fn check_class(&mut self, class: &Class, name: &str) {
+        // Field initializers can use private members, but no variables
+        let mut scope = Scope {
+            class: Some(name.to_string()),
+            ..Default::default()
+        };
+        let kinds: Vec<Type> = self.classes[name]
+            .fields
+            .iter()
+            .map(|x| x.r#type.clone())
+            .collect();
+        for (field, kind) in class.fields.iter().zip(kinds) {
+            let value = self.check_expr(&field.value, &mut scope);
+            if !kind.accepts(&value) {
+                self.problem(
+                    field.value.span(),
+                    format!(
+                        "Field `{}` of `{}` is declared as a `{}` but got a `{}`",
+                        field.declaration.ident.0, name, kind, value
+                    ),
+                );
+            }
+        }
+
+        for method in &class.methods {
+            let function = method_function_name(name, &method.declaration.ident.0);
+            self.check_function(method, &function, Some(name));
+        }
+    }
+
+    /// Checks the body of a function declared as `name`. Methods of `class` take the object as
+    /// `me` before their other arguments
+    pub fn check_function(&mut self, function: &Function, name: &str, class: Option<&str>) {
+        let signature = self.functions[name].clone();
+        let mut scope = Scope {
+            function: Some(name.to_string()),
+            class: class.map(str::to_string),
+            ..Default::default()
+        };
+        let names = class
+            .map(|_| "me")
+            .into_iter()
+            .chain(function.args.iter().map(|x| x.ident.0.as_str()));
+        for (ident, r#type) in names.zip(signature.params) {
+            self.declare(&mut scope, ident, r#type.clone(), r#type);
+        }
+
+        self.check_tree(&function.body, &mut scope);
+
+        if !matches!(signature.r#return, Type::Any | Type::Null) && !leaves(&function.body, false) {
+            self.problem(
+                function.declaration.ident.1,
+                format!(
+                    "Function `{}` must `spez` a `{}` at the end of its body",
+                    name, signature.r#return
+                ),
+            );
+        }
+    }
+
+    fn check_assignment(&mut self, assignment: &Assignment, scope: &mut Scope) {
+        let ident = &assignment.ident;
+        let variable = match scope.variables.get(&ident.0) {
+            Some(x) => x.clone(),
+            None => {
+                self.problems.push(
+                    Diagnostic::error(format!("Variable `{}` is not defined", ident.0))
+                        .with_span(ident.1)
+                        .with_help(format!("Declare it with `meth {} ∑ ...` first", ident.0)),
+                );
+                self.check_expr(&assignment.value, scope);
+                return;
+            }
+        };
+
+        // `a.b[i] ∑ x` stores into the array in `a.b`
+        if let Some((last, path)) = assignment.indexes.split_last() {
+            let mut container = variable.holds;
+            for member in &assignment.members {
+                container = self.check_member(container, member, scope);
+            }
+            for index in path {
+                let index_type = self.check_expr(index, scope);
+                container = self.check_index(container, index_type, index.span());
+            }
+
+            let index_type = self.check_expr(last, scope);
+            self.check_index(container.clone(), index_type, last.span());
+            let value = self.check_expr(&assignment.value, scope);
+            if let Type::Array(item) = &container {
+                if !item.accepts(&value) {
+                    self.problem(
+                        assignment.value.span(),
+                        format!("Can not put a `{}` in a `{}`", value, container),
+                    );
+                }
+            }
+            return;
+        }
+
+        // `a.b.c ∑ x` stores into the field `c` of `a.b`
+        let (name, declared) = match assignment.members.split_last() {
+            Some((field, path)) => {
+                let mut object = variable.holds;
+                for member in path {
+                    object = self.check_member(object, member, scope);
+                }
+                (&field.0, self.check_member(object, field, scope))
+            }
+            None => (&ident.0, variable.declared),
+        };
+
+        let value = self.check_expr(&assignment.value, scope);
+        if !declared.accepts(&value) {
+            self.problem(
+                assignment.value.span(),
+                format!(
+                    "Can not assign a `{}` to `{}`, it was declared as a `{}`",
+                    value, name, declared
+                ),
+            );
+        }
+
+        if assignment.members.is_empty() {
+            let holds = declared.holding(value);
+            scope.variables.get_mut(&ident.0).unwrap().holds = holds;
+        }
+    }
+
+    /// What a call returns, `wat` when the function returns nothing
+    fn check_call(&mut self, call: &Call, scope: &mut Scope) -> Type {
+        // Methods are called with the object as the first argument
+        let (name, is_method) = match &call.receiver {
+            Some(receiver) => {
+                let object = self.check_term(receiver, scope);
+                match self.check_method(object, &call.ident, scope) {
+                    Some(x) => (x, true),
+                    None => return self.check_args(call, scope),
+                }
+            }
+            None => (self.namespace.resolve(&call.ident.0), false),
+        };
+
+        if self.redeclared.contains(&name) {
+            return self.check_args(call, scope);
+        }
+        let signature = match self.functions.get(&name) {
+            Some(x) => x.clone(),
+            None => {
+                self.problem(
+                    call.ident.1,
+                    format!("Function `{}` not defined", call.ident.0),
+                );
+                return self.check_args(call, scope);
+            }
+        };
+
+        let params = &signature.params[is_method as usize..];
+        if call.args.len() != params.len() {
+            self.problem(
+                call.span,
+                format!(
+                    "`{}` takes {} argument{} but got {}",
+                    call.ident.0,
+                    params.len(),
+                    if params.len() == 1 { "" } else { "s" },
+                    call.args.len()
+                ),
+            );
+        }
+
+        for (i, arg) in call.args.iter().enumerate() {
+            let value = self.check_expr(arg, scope);
+            match params.get(i) {
+                Some(kind) if !kind.accepts(&value) => self.problem(
+                    arg.span(),
+                    format!(
+                        "Argument {} of `{}` must be a `{}` but got a `{}`",
+                        i + 1,
+                        call.ident.0,
+                        kind,
+                        value
+                    ),
+                ),
+                _ => {}
+            }
+        }
+        signature.r#return
+    }
+
+    /// Checks the arguments of a call to something unknown, which returns a dynamically typed value
+    fn check_args(&mut self, call: &Call, scope: &mut Scope) -> Type {
+        for arg in &call.args {
+            self.check_expr(arg, scope);
+        }
+        Type::Any
+    }
+
+    /// The class of an object that `member` is used on, `None` when its members can not be
+    /// checked
+    fn object_class(&mut self, object: Type, member: &Ident) -> Option<String> {
+        match object {
+            Type::Object(x) if self.redeclared.contains(&x) => {}
+            Type::Object(x) => return Some(x),
+            Type::Any => self.problems.push(
+                Diagnostic::error(format!(
+                    "Can not find `{}` on a dynamically typed value",
+                    member.0
+                ))
+                .with_span(member.1)
+                .with_help("Give it a class with `damn`"),
+            ),
+            x => self.problem(
+                member.1,
+                format!("A `{}` has no members, it can not have `{}`", x, member.0),
+            ),
+        }
+        None
+    }
+
+    fn check_visibility(&mut self, scope: &Scope, class: &str, public: bool, member: &Ident) {
+        if !public && scope.class.as_deref() != Some(class) {
+            self.problems.push(
+                Diagnostic::error(format!("`{}` of `{}` is private", member.0, class))
+                    .with_span(member.1)
+                    .with_help("Mark it with `bar` to use it outside of the class"),
+            );
+        }
+    }
+
+    /// What the field `member` of `object` holds
+    fn check_member(&mut self, object: Type, member: &Ident, scope: &Scope) -> Type {
+        let class = match self.object_class(object, member) {
+            Some(x) => x,
+            None => return Type::Any,
+        };
+        let field = self.classes[&class]
+            .fields
+            .iter()
+            .find(|x| x.ident == member.0)
+            .map(|x| (x.r#type.clone(), x.public));
+        match field {
+            Some((r#type, public)) => {
+                self.check_visibility(scope, &class, public, member);
+                r#type
+            }
+            None => {
+                self.problem(
+                    member.1,
+                    format!("Class `{}` has no field `{}`", class, member.0),
+                );
+                Type::Any
+            }
+        }
+    }
+
+    /// The function to call for `method` on `object`
+    fn check_method(&mut self, object: Type, method: &Ident, scope: &Scope) -> Option<String> {
+        let class = self.object_class(object, method)?;
+        match self.classes[&class].methods.get(&method.0) {
+            Some(public) => {
+                let public = *public;
+                self.check_visibility(scope, &class, public, method);
+                Some(method_function_name(&class, &method.0))
+            }
+            None => {
+                self.problem(
+                    method.1,
+                    format!("Class `{}` has no method `{}`", class, method.0),
+                );
+                None
+            }
+        }
+    }
+
+    fn check_variable(&mut self, ident: &Ident, scope: &Scope) -> Type {
+        match scope.variables.get(&ident.0) {
+            Some(x) => x.holds.clone(),
+            None => {
+                self.problem(ident.1, format!("Variable `{}` not defined", ident.0));
+                Type::Any
+            }
+        }
+    }
+
+    /// What `container[index]` holds, items of arrays and maps are dynamically typed
+    fn check_index(&mut self, container: Type, index: Type, span: Span) -> Type {
+        let item = match &container {
+            Type::Array(x) => x.dynamic(),
+            _ => Type::Any,
+        };
+        let message = match (container, index) {
+            (Type::Array(_) | Type::Map | Type::Any, Type::Any)
+            | (Type::Array(_), Type::Number)
+            | (Type::Map, Type::String)
+            | (Type::Any, Type::Number | Type::String) => None,
+            (Type::Array(_), x) => {
+                Some(format!("An array index must be a `Number`, not a `{}`", x))
+            }
+            (Type::Map, x) => Some(format!("A map key must be a `String`, not a `{}`", x)),
+            (Type::Any, x) => Some(format!("A `{}` can not be used as an index", x)),
+            (x, _) => Some(format!("A `{}` can not be indexed", x)),
+        };
+        if let Some(message) = message {
+            self.problem(span, message);
+        }
+        item
+    }
+
+    /// Numbers are used as is, dynamically typed values are assumed to hold a number
+    fn check_number(&mut self, value: Type, span: Span) {
+        if !matches!(value, Type::Number | Type::Any) {
+            self.problem(span, format!("Can not do math with a `{}`", value));
+        }
+    }
+
+    /// Like `compare` in the compiler, equality works for any two values
+    fn check_compare(
+        &mut self,
+        left: Type,
+        operator: &ConditionalOperator,
+        right: Type,
+        span: Span,
+    ) {
+        if matches!(
+            operator,
+            ConditionalOperator::Equality | ConditionalOperator::AntiEquality
+        ) {
+            return;
+        }
+
+        let r#type = match (left, right) {
+            // Ordered by the runtime, which checks the tags
+            (Type::Any, Type::Any) => return,
+            (Type::Any, x) | (x, Type::Any) => x,
+            (l, r) if l == r => l,
+            (l, r) => {
+                self.problem(span, format!("Can not compare a `{}` with a `{}`", l, r));
+                return;
+            }
+        };
+        if !matches!(r#type, Type::Number | Type::Boolean | Type::String) {
+            self.problem(span, format!("`{}` values can not be ordered", r#type));
+        }
+    }
+
+    pub fn check_expr(&mut self, expr: &Expr, scope: &mut Scope) -> Type {
+        match expr {
+            Expr::UnaryExpr(x) => {
+                let operand = self.check_expr(&x.operand, scope);
+                match x.operator {
+                    UnaryOperator::Positive | UnaryOperator::Negative => {
+                        self.check_number(operand, x.operand.span());
+                        Type::Number
+                    }
+                    UnaryOperator::Negation => Type::Boolean,
+                }
+            }
+            Expr::BinaryExpr(x) => {
+                let left = self.check_expr(&x.left, scope);
+                let right = self.check_expr(&x.right, scope);
+                if let MathOperator::Add = x.operator {
+                    return match (left, right) {
+                        (Type::String, Type::String) => Type::String,
+                        (Type::Any, Type::Any | Type::Number | Type::String)
+                        | (Type::Number | Type::String, Type::Any) => Type::Any,
+                        (Type::String, other) | (other, Type::String) => {
+                            self.problem(
+                                x.span,
+                                format!("Can not add a `{}` and a `String`", other),
+                            );
+                            Type::Any
+                        }
+                        (left, right) => {
+                            self.check_number(left, x.left.span());
+                            self.check_number(right, x.right.span());
+                            Type::Number
+                        }
+                    };
+                }
+
+                self.check_number(left, x.left.span());
+                self.check_number(right, x.right.span());
+                Type::Number
+            }
+            Expr::ConditionalExpr(x) => {
+                // `a < b < c` is `a < b` and `b < c`
+                let operands: Vec<Type> = x
+                    .terms
+                    .iter()
+                    .map(|x| self.check_expr(&x.operand, scope))
+                    .collect();
+                for (i, term) in x.terms.iter().enumerate() {
+                    if let Some(operator) = &term.operator {
+                        let span = term.operand.span().to(x.terms[i + 1].operand.span());
+                        self.check_compare(
+                            operands[i].clone(),
+                            operator,
+                            operands[i + 1].clone(),
+                            span,
+                        );
+                    }
+                }
+                Type::Boolean
+            }
+            Expr::IndexExpr(x) => {
+                let mut value = self.check_term(&x.term, scope);
+                for index in &x.indexes {
+                    let index_type = self.check_expr(index, scope);
+                    value = self.check_index(value, index_type, index.span());
+                }
+                value
+            }
+            Expr::Term(x, _) => self.check_term(x, scope),
+            Expr::Null(_) => Type::Null,
+        }
+    }
+
+    fn check_term(&mut self, term: &Term, scope: &mut Scope) -> Type {
+        match term {
+            Term::Number(_) => Type::Number,
+            Term::String(_) => Type::String,
+            Term::Boolean(_) => Type::Boolean,
+            Term::Foolean(_) => Type::Foolean,
+            Term::Member(x) => {
+                let mut object = self.check_variable(&x.object, scope);
+                for member in &x.members {
+                    object = self.check_member(object, member, scope);
+                }
+                object
+            }
+            Term::Ident(x) => self.check_variable(x, scope),
+            Term::Call(x) => self.check_call(x, scope),
+            Term::Array(items) => {
+                let items: Vec<Type> = items.iter().map(|x| self.check_expr(x, scope)).collect();
+                let item = match items.split_first() {
+                    Some((first, rest)) => rest.iter().fold(first.clone(), |x, y| x.join(y)),
+                    None => Type::Any,
+                };
+                Type::Array(Box::new(item))
+            }
+            Term::Map(entries) => {
+                for (key, value) in entries {
+                    let key_type = self.check_expr(key, scope);
+                    self.check_index(Type::Map, key_type, key.span());
+                    self.check_expr(value, scope);
+                }
+                Type::Map
+            }
+            Term::Expr(x) => self.check_expr(x, scope),
+        }
+    }
+}
+
+/// Whether the end of `tree` can not be reached, because every way through it leaves with `spez`,
+/// `shoot` or `sthu`, or loops forever. A `sthu` only leaves when the tree is `in_loop`, any other
+/// one is a problem of its own
+pub fn leaves(tree: &[Node], in_loop: bool) -> bool {
+    tree.iter().any(|node| match node {
+        Node::Return(_) | Node::Throw(_) => true,
+        Node::Break(_) => in_loop,
+        Node::If(x) => {
+            x.if_nodes.iter().any(|x| matches!(x, IfNode::Else(_)))
+                && x.if_nodes.iter().all(|x| match x {
+                    IfNode::Case(x) => leaves(&x.body, in_loop),
+                    IfNode::Else(x) => leaves(&x.body, in_loop),
+                })
+        }
+        Node::TryCatch(x) => leaves(&x.r#try.0, in_loop) && leaves(&x.catch.1, in_loop),
+        // A loop over nothing runs until a `sthu`
+        Node::Loop(x) => x.each.is_none() && !breaks(&x.body),
+        _ => false,
+    })
+}
+
+/// Whether `tree` has a `sthu` for the loop it is in
+fn breaks(tree: &Tree) -> bool {
+    tree.iter().any(|node| match node {
+        Node::Break(_) => true,
+        Node::If(x) => x.if_nodes.iter().any(|x| match x {
+            IfNode::Case(x) => breaks(&x.body),
+            IfNode::Else(x) => breaks(&x.body),
+        }),
+        Node::TryCatch(x) => breaks(&x.r#try.0) || breaks(&x.catch.1),
+        _ => false,
+    })
+}
+
+/// Forgets what `idents` hold, they can hold anything they were declared as
+fn forget(scope: &mut Scope, idents: &[&str]) {
+    for ident in idents {
+        if let Some(variable) = scope.variables.get_mut(*ident) {
+            variable.holds = variable.declared.clone();
+        }
+    }
+}
+
+/// After one of `branches` ran, each starting from `scope`, a variable holds what it held at the end
+/// of any of them
+fn merge(scope: &mut Scope, branches: &[Scope]) {
+    for (ident, variable) in scope.variables.iter_mut() {
+        let holds = branches
+            .iter()
+            .map(|branch| match branch.variables.get(ident) {
+                Some(x) if x.id == variable.id => x.holds.clone(),
+                // Shadowed, it could have been assigned before that
+                _ => variable.declared.clone(),
+            });
+        if let Some(holds) = holds.reduce(|x, y| x.join(&y)) {
+            variable.holds = holds;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        modules::{SourceFile, SourceModule},
+        parser::parse_file,
+        sema::{types::Type, Sema},
+    };
+
+    /// `source` as `main.rl`
+    fn main_module(source: &str) -> SourceModule {
+        let file = Rc::new(SourceFile {
+            path: "src/main.rl".to_string(),
+            code: source.to_string(),
+        });
+        SourceModule {
+            name: None,
+            tree: parse_file(&file).unwrap().0,
+            file,
+            imports: vec![],
+        }
+    }
+
+    /// The messages of the problems sema finds in `source`, checked as `main.rl`
+    fn problems(source: &str) -> Vec<String> {
+        Sema::new()
+            .check_module(&main_module(source))
+            .into_iter()
+            .map(|x| x.message)
+            .collect()
+    }
+
+    #[test]
+    fn valid_programs_have_no_problems() {
+        let source = r#"
+callmeonmycellphone add damn Number(a damn Number, b,) {
+    spez a ⨋ b
+}
+meth n ∑ call add(1, 2,)
+n ∑ call add(n, n,)
+"#;
+        assert_eq!(problems(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn undefined_names_are_problems() {
+        assert_eq!(
+            problems("call nope()\nmeth x ∑ y"),
+            ["Function `nope` not defined", "Variable `y` not defined"]
+        );
+    }
+
+    #[test]
+    fn calls_are_checked_against_the_signature() {
+        let source = r#"
+callmeonmycellphone twice(x damn Number,) {}
+call twice()
+call twice(1, 2,)
+call twice("one",)
+"#;
+        assert_eq!(
+            problems(source),
+            [
+                "`twice` takes 1 argument but got 0",
+                "`twice` takes 1 argument but got 2",
+                "Argument 1 of `twice` must be a `Number` but got a `String`"
+            ]
+        );
+    }
+
+    #[test]
+    fn names_can_only_be_defined_once() {
+        let source = r#"
+callmeonmycellphone twice() {}
+callmeonmycellphone twice() {}
+school Twice {
+    meth a ∑ 1
+    meth a ∑ 2
+}
+"#;
+        assert_eq!(
+            problems(source),
+            [
+                "Class `Twice` has more than one `a`",
+                "`twice` is already defined"
+            ]
+        );
+    }
+
+    #[test]
+    fn private_members_can_only_be_used_in_their_class() {
+        let source = r#"
+school Counter {
+    meth count damn Number ∑ 0
+    bar callmeonmycellphone tick() {
+        me.count ∑ me.count ⨋ 1
+    }
+}
+meth counter damn Counter ∑ call Counter()
+call counter.tick()
+meth n ∑ counter.count
+"#;
+        assert_eq!(problems(source), ["`count` of `Counter` is private"]);
+    }
+
+    #[test]
+    fn unknown_types_are_problems() {
+        assert_eq!(problems("meth x damn Nope ∑ 1"), ["Unknown type `Nope`"]);
+    }
+
+    #[test]
+    fn annotations_are_checked() {
+        let source = r#"
+callmeonmycellphone name damn String(n damn Number,) {
+    spez n
+}
+meth n damn Number ∑ call name("one",)
+"#;
+        assert_eq!(
+            problems(source),
+            [
+                "Function `name` must `spez` a `String` but got a `Number`",
+                "Argument 1 of `name` must be a `Number` but got a `String`",
+                "Variable `n` is declared as a `Number` but got a `String`"
+            ]
+        );
+    }
+
+    #[test]
+    fn typed_functions_must_spez_at_the_end() {
+        assert_eq!(
+            problems("callmeonmycellphone one damn Number() {}"),
+            ["Function `one` must `spez` a `Number` at the end of its body"]
+        );
+    }
+
+    #[test]
+    fn only_a_sthu_inside_of_a_loop_leaves_it() {
+        assert_eq!(
+            problems("repeatdatshid {\n    sthu\n    meth x ∑ 1\n}"),
+            ["This code is never run"]
+        );
+        assert_eq!(
+            problems("sthu\nmeth x ∑ 1"),
+            ["`sthu` can only be used inside of a loop"]
+        );
+    }
+
+    #[test]
+    fn untyped_variables_hold_what_they_were_given() {
+        assert_eq!(
+            problems("meth x ∑ \"a\"\nmeth y ∑ x – 1"),
+            ["Can not do math with a `String`"]
+        );
+    }
+
+    #[test]
+    fn declared_types_of_variables_are_kept() {
+        let mut sema = Sema::new();
+        sema.check_module(&main_module("meth x damn Number[] ∑ [1]\nmeth y ∑ 1"));
+        let mut variables: Vec<_> = sema.variables.into_iter().collect();
+        variables.sort_by_key(|x| x.0 .1);
+        assert_eq!(
+            variables,
+            [
+                ((None, 5), Type::Array(Box::new(Type::Number))),
+                ((None, 34), Type::Any)
+            ]
+        );
+    }
+
+    #[test]
+    fn variables_assigned_in_a_loop_can_hold_anything() {
+        let source = r#"
+meth x ∑ "a"
+repeatdatshid {
+    x ∑ 1
+    sthu
+}
+meth y ∑ x – 1
+"#;
+        assert_eq!(problems(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn arrays_only_take_items_of_their_type() {
+        let source = r#"
+meth nums damn Number[] ∑ [1, 2]
+nums[1] ∑ "three"
+meth empty damn String[] ∑ []
+meth words damn String[] ∑ [1, 2]
+"#;
+        assert_eq!(
+            problems(source),
+            [
+                "Can not put a `String` in a `Number[]`",
+                "Variable `words` is declared as a `String[]` but got a `Number[]`"
+            ]
+        );
+    }
+
+    #[test]
+    fn names_defined_twice_are_reported_once() {
+        let source = r#"
+callmeonmycellphone twice(x,) {}
+callmeonmycellphone twice() {}
+call twice(1, 2, 3,)
+"#;
+        assert_eq!(problems(source), ["`twice` is already defined"]);
+    }
+
+    #[test]
+    fn untyped_values_are_ordered_when_the_program_runs() {
+        let source = r#"
+callmeonmycellphone lt(a, b,) {
+    spez a < b
+}
+meth words ∑ "a" < "b"
+meth wrong ∑ "a" < 1
+"#;
+        assert_eq!(
+            problems(source),
+            ["Can not compare a `String` with a `Number`"]
+        );
+    }
+
+    #[test]
+    fn only_strings_can_be_printed() {
+        assert_eq!(
+            problems("call coitusinterruptus(1,)"),
+            ["Argument 1 of `coitusinterruptus` must be a `String` but got a `Number`"]
+        );
+    }
+}
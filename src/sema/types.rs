@@ -0,0 +1,156 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use crate::parser::{IfNode, Node, Tree};
+
+/// What is known about a value before the program runs. The compiler lowers it to a
+/// [`crate::compiler::value::ValueKind`], which forgets what arrays hold
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Number,
+    Boolean,
+    Foolean,
+    String,
+    /// `Item[]`, items of unknown type are `Any`
+    Array(Box<Type>),
+    Map,
+    Null,
+    Any,
+    /// An instance of the class with this name
+    Object(String),
+}
+
+/// The parameters and return type of a function
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub params: Vec<Type>,
+    /// `wat` when the function returns nothing
+    pub r#return: Type,
+    /// Written in another language, like the standard library. Strings are passed as their bytes
+    pub foreign: bool,
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub ident: String,
+    pub r#type: Type,
+    pub public: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ClassInfo {
+    /// In the order they are declared, which is how objects store them
+    pub fields: Vec<Field>,
+    /// Method names and whether they are `bar`
+    pub methods: HashMap<String, bool>,
+}
+
+/// A variable that is in scope
+#[derive(Debug, Clone)]
+pub struct Variable {
+    /// Tells apart variables with the same name, when one shadows the other
+    pub id: usize,
+    /// The `damn` annotation, `Any` without one
+    pub declared: Type,
+    /// What the variable holds at this point of the program, as far as is known
+    pub holds: Type,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Array(x) if **x != Type::Any => write!(f, "{}[]", x),
+            Type::Array(_) => write!(f, "Array"),
+            Type::Number => write!(f, "Number"),
+            Type::Boolean => write!(f, "Boolean"),
+            Type::Foolean => write!(f, "Foolean"),
+            Type::String => write!(f, "String"),
+            Type::Map => write!(f, "Map"),
+            Type::Null => write!(f, "wat"),
+            Type::Any => write!(f, "Any"),
+            Type::Object(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl Type {
+    /// Whether `other` can be used where a `self` is expected, like
+    /// [`crate::compiler::value::ValueKind::accepts`]. Arrays accept arrays whose items they accept
+    pub fn accepts(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Array(x), Type::Array(y)) => x.accepts(y),
+            (x, y) => {
+                x == y
+                    || *x == Type::Any
+                    || *y == Type::Any
+                    || (*x == Type::Foolean && *y == Type::Boolean)
+                    || (matches!(x, Type::Object(_) | Type::Foolean) && *y == Type::Null)
+            }
+        }
+    }
+
+    /// What a value of either type is
+    pub fn join(&self, other: &Type) -> Type {
+        match (self, other) {
+            (x, y) if x == y => x.clone(),
+            (Type::Array(x), Type::Array(y)) => Type::Array(Box::new(x.join(y))),
+            _ => Type::Any,
+        }
+    }
+
+    /// What is known about the value after it was kept in a dynamically typed variable or item.
+    /// Objects need a `damn` class to be used and arrays forget what they hold, so they can hold
+    /// anything
+    pub fn dynamic(&self) -> Type {
+        match self {
+            Type::Object(_) => Type::Any,
+            Type::Array(_) => Type::Array(Box::new(Type::Any)),
+            x => x.clone(),
+        }
+    }
+
+    /// What a variable declared as `self` holds after `value` is stored in it
+    pub fn holding(&self, value: Type) -> Type {
+        match self {
+            Type::Any => value.dynamic(),
+            x => x.clone(),
+        }
+    }
+
+    /// What `repeatdatshid x ∈ collection` gives `x`, the keys of a map are strings
+    pub fn item(&self) -> Type {
+        match self {
+            Type::Array(x) => x.dynamic(),
+            Type::Map => Type::String,
+            _ => Type::Any,
+        }
+    }
+}
+
+/// Variables that `tree` assigns to with `x ∑ ...`, in its blocks too. They can hold anything they
+/// were declared as after a loop or `test` that assigns them
+pub fn assigned(tree: &Tree) -> Vec<&str> {
+    let mut idents = vec![];
+    for node in tree {
+        match node {
+            Node::Assignment(x) if x.members.is_empty() && x.indexes.is_empty() => {
+                idents.push(x.ident.0.as_str())
+            }
+            Node::Loop(x) => idents.extend(assigned(&x.body)),
+            Node::TryCatch(x) => {
+                idents.extend(assigned(&x.r#try.0));
+                idents.extend(assigned(&x.catch.1));
+            }
+            Node::If(x) => {
+                for if_node in &x.if_nodes {
+                    match if_node {
+                        IfNode::Case(x) => idents.extend(assigned(&x.body)),
+                        IfNode::Else(x) => idents.extend(assigned(&x.body)),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    idents
+}
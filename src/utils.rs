@@ -1,12 +0,0 @@
-use std::collections::HashSet;
-use std::hash::Hash;
-
-/// Checks if each item in an iterators is unique
-pub fn is_unique<T>(iter: T) -> bool
-where
-    T: IntoIterator,
-    T::Item: Eq + Hash,
-{
-    let mut uniq = HashSet::new();
-    iter.into_iter().all(move |x| uniq.insert(x))
-}
@@ -0,0 +1,1169 @@
+//! Cooks example projects with `walter` and runs them. Needs what `walter cook` needs, LLVM and a C
+//! compiler, and builds the standard library like any project does
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::Mutex,
+};
+
+/// Cooking pulls and builds the standard library in the same directory, so only one project is
+/// cooked at a time
+static COOKING: Mutex<()> = Mutex::new(());
+
+/// What a program printed and the status it exited with
+struct Run {
+    stdout: String,
+    stderr: String,
+    status: i32,
+}
+
+/// A fresh project called `name`, `files` are placed in its `src` directory
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+
+    for (file, source) in files {
+        let path = dir.join("src").join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    fs::write(
+        dir.join("walter.yml"),
+        format!("name: {}\nversion: 0.0.1\n", name),
+    )
+    .unwrap();
+    dir
+}
+
+fn cook(dir: &Path, args: &[&str]) -> Output {
+    let _cooking = COOKING.lock().unwrap_or_else(|x| x.into_inner());
+    Command::new(env!("CARGO_BIN_EXE_walter"))
+        .arg("cook")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+/// Cooks a project and runs it, `args` are passed to `walter cook`
+fn run_with(name: &str, files: &[(&str, &str)], args: &[&str]) -> Run {
+    let dir = project(name, files);
+    let output = cook(&dir, args);
+    assert!(
+        output.status.success(),
+        "`{}` did not compile:\n{}",
+        name,
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let build = if args.contains(&"--release") {
+        "release"
+    } else {
+        "debug"
+    };
+    let output = Command::new(dir.join("build").join(build).join(name))
+        .output()
+        .unwrap();
+    Run {
+        stdout: String::from_utf8(output.stdout).unwrap(),
+        stderr: String::from_utf8(output.stderr).unwrap(),
+        status: output.status.code().unwrap(),
+    }
+}
+
+/// Cooks a debug build of a project whose only file is `main.rl` and runs it
+fn run(name: &str, source: &str) -> Run {
+    run_with(name, &[("main.rl", source)], &[])
+}
+
+/// Cooks a project that must not compile, returning what `walter` printed
+fn cook_error_with(name: &str, files: &[(&str, &str)]) -> String {
+    let dir = project(name, files);
+    let output = cook(&dir, &[]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(!stdout.contains("CompilerBug"), "{}", stdout);
+    stdout
+}
+
+/// Cooks a project whose only file is `main.rl` that must not compile
+fn cook_error(name: &str, source: &str) -> String {
+    cook_error_with(name, &[("main.rl", source)])
+}
+
+#[test]
+fn hello_world() {
+    let run = run("hello_world", include_str!("../examples/hello_world.rl"));
+    assert_eq!(run.stdout, "Hello, World\n");
+    assert_eq!(run.status, 0);
+}
+
+#[test]
+fn functions_can_be_called_before_they_are_defined() {
+    let run = run(
+        "call_before_definition",
+        r#"
+call greet(1,)
+
+callmeonmycellphone greet(times damn Number,) {
+    call coitusinterruptus("hi",)
+}
+
+call greet(2,)
+"#,
+    );
+    assert_eq!(run.stdout, "hi\nhi\n");
+    assert_eq!(run.status, 0);
+}
+
+#[test]
+fn functions_without_spez_return() {
+    let run = run(
+        "no_spez",
+        r#"
+callmeonmycellphone nothing() {}
+call nothing()
+call coitusinterruptus("after",)
+"#,
+    );
+    assert_eq!(run.stdout, "after\n");
+}
+
+#[test]
+fn undefined_functions_are_errors() {
+    let stdout = cook_error("undefined_function", "call nope()");
+    assert!(stdout.contains("Function `nope` not defined"), "{}", stdout);
+}
+
+#[test]
+fn top_level_spez_is_the_exit_status() {
+    let run = run("exit_status", "spez 7");
+    assert_eq!(run.status, 7);
+}
+
+#[test]
+fn calls_are_expressions() {
+    let run = run(
+        "call_expression",
+        r#"
+callmeonmycellphone seven damn Number() {
+    spez 7
+}
+spez call seven()
+"#,
+    );
+    assert_eq!(run.status, 7);
+}
+
+#[test]
+fn spez_must_match_the_return_type() {
+    let stdout = cook_error(
+        "wrong_return",
+        r#"
+callmeonmycellphone seven damn Number() {
+    spez "seven"
+}
+"#,
+    );
+    assert!(
+        stdout.contains("Function `seven` must `spez` a `Number` but got a `String`"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn is_takes_the_first_true_arm() {
+    let run = run(
+        "if_chain",
+        r#"
+is 0 {
+    spez 1
+} but 2 {
+    call coitusinterruptus("second",)
+} but 3 {
+    spez 3
+} isn't {
+    spez 4
+}
+spez 5
+"#,
+    );
+    assert_eq!(run.stdout, "second\n");
+    assert_eq!(run.status, 5);
+}
+
+#[test]
+fn isnt_runs_when_no_arm_is_true() {
+    let run = run(
+        "if_else",
+        r#"
+callmeonmycellphone pick damn Number() {
+    is 0 {
+        spez 1
+    } isn't {
+        spez 2
+    }
+}
+spez call pick()
+"#,
+    );
+    assert_eq!(run.status, 2);
+}
+
+#[test]
+fn comparisons() {
+    let run = run(
+        "comparisons",
+        r#"
+is 2 ⅀ 2 { call coitusinterruptus("⅀",) }
+is 2 ≠ 3 { call coitusinterruptus("≠",) }
+is 3 > 2 { call coitusinterruptus(">",) }
+is 2 ⋝ 2 { call coitusinterruptus("⋝",) }
+is 2 < 3 { call coitusinterruptus("<",) }
+is 2 ⋜ 2 { call coitusinterruptus("⋜",) }
+is 2 ⅀ 3 { call coitusinterruptus("wrong ⅀",) }
+is 2 ≠ 2 { call coitusinterruptus("wrong ≠",) }
+is 2 > 2 { call coitusinterruptus("wrong >",) }
+is 2 ⋝ 3 { call coitusinterruptus("wrong ⋝",) }
+is 2 < 2 { call coitusinterruptus("wrong <",) }
+is 3 ⋜ 2 { call coitusinterruptus("wrong ⋜",) }
+"#,
+    );
+    assert_eq!(run.stdout, "⅀\n≠\n>\n⋝\n<\n⋜\n");
+}
+
+#[test]
+fn chained_comparisons_compare_each_pair() {
+    let run = run(
+        "chained_comparisons",
+        r#"
+callmeonmycellphone two damn Number() {
+    call coitusinterruptus("two",)
+    spez 2
+}
+is 1 < call two() < 3 { call coitusinterruptus("1 < 2 < 3",) }
+is 3 > 2 > 2 { call coitusinterruptus("wrong 3 > 2 > 2",) }
+is 1 < 3 > 2 { call coitusinterruptus("1 < 3 > 2",) }
+"#,
+    );
+    assert_eq!(run.stdout, "two\n1 < 2 < 3\n1 < 3 > 2\n");
+}
+
+#[test]
+fn math_on_call_results() {
+    let run = run(
+        "call_math",
+        r#"
+callmeonmycellphone five damn Number() {
+    spez 5
+}
+spez call five() ⋇ 4 ⎲ 2 – call five() ⨋ 1
+"#,
+    );
+    assert_eq!(run.status, 6);
+}
+
+#[test]
+fn xor_truncates_its_operands() {
+    let run = run(
+        "xor",
+        r#"
+callmeonmycellphone five damn Number() {
+    spez 5
+}
+spez call five() ⊕ 3.7
+"#,
+    );
+    assert_eq!(run.status, 6);
+}
+
+#[test]
+fn precedence() {
+    let run = run("precedence", "spez 1 ⨋ 2 ⋇ (3 ⨋ 1) – 6 ⎲ (1 ⨋ 2)");
+    assert_eq!(run.status, 7);
+}
+
+#[test]
+fn unary_operators() {
+    let run = run(
+        "unary",
+        r#"
+callmeonmycellphone three damn Number() {
+    spez 3
+}
+is ¡0 { call coitusinterruptus("¡0",) }
+is ¡¡"text" { call coitusinterruptus("¡¡text",) }
+is ¡call three() { call coitusinterruptus("wrong ¡3",) }
+spez 10 ⨋ – call three() ⋇ ⨋ 2
+"#,
+    );
+    assert_eq!(run.stdout, "¡0\n¡¡text\n");
+    assert_eq!(run.status, 4);
+}
+
+#[test]
+fn assigning_an_undeclared_variable_is_an_error() {
+    let stdout = cook_error("undeclared_assignment", "nope ∑ 1");
+    assert!(
+        stdout.contains("Variable `nope` is not defined"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("help: Declare it with `meth nope ∑ ...` first"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn assignments_must_match_the_declared_type() {
+    let stdout = cook_error(
+        "assignment_type",
+        r#"
+meth s damn String ∑ "a"
+s ∑ 1
+"#,
+    );
+    assert!(
+        stdout.contains("Can not assign a `Number` to `s`, it was declared as a `String`"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn variables_can_be_reassigned() {
+    let run = run(
+        "reassignment",
+        r#"
+meth s damn String ∑ "a"
+s ∑ "b"
+"#,
+    );
+    assert_eq!(run.status, 0);
+}
+
+#[test]
+fn variables_can_be_read_back() {
+    let run = run(
+        "read_variables",
+        r#"
+meth n ∑ 4
+meth typed damn Number ∑ 2
+meth s ∑ "text"
+call coitusinterruptus(s,)
+n ∑ n ⨋ typed
+spez n
+"#,
+    );
+    assert_eq!(run.stdout, "text\n");
+    assert_eq!(run.status, 6);
+}
+
+#[test]
+fn variables_in_loops_do_not_grow_the_stack() {
+    let run = run(
+        "loop_variables",
+        r#"
+meth i ∑ 0
+repeatdatshid {
+    is i ⅀ 3000000 { sthu }
+    meth j ∑ i ⨋ 1
+    i ∑ j
+}
+spez 7
+"#,
+    );
+    assert_eq!(run.status, 7);
+}
+
+#[test]
+fn booleans_and_fooleans() {
+    let run = run(
+        "booleans",
+        r#"
+is Yup { call coitusinterruptus("Yup",) }
+is Nope { call coitusinterruptus("wrong Nope",) }
+meth f damn Foolean ∑ Yup
+is f { call coitusinterruptus("Foolean Yup",) }
+f ∑ Dunno
+is f { call coitusinterruptus("wrong Dunno",) }
+f ∑ Huh
+is f { call coitusinterruptus("wrong Huh",) }
+is Dunno ⅀ wat { call coitusinterruptus("Dunno is wat",) }
+"#,
+    );
+    assert_eq!(run.stdout, "Yup\nFoolean Yup\nDunno is wat\n");
+}
+
+#[test]
+fn yeet_is_decided_when_the_program_runs() {
+    let run = run(
+        "yeet",
+        r#"
+meth i ∑ 0
+meth truthy ∑ 0
+repeatdatshid {
+    is i ⅀ 200 { sthu }
+    is Yeet { truthy ∑ truthy ⨋ 1 }
+    i ∑ i ⨋ 1
+}
+is truthy > 0 {
+    is truthy < 200 { spez 1 }
+}
+"#,
+    );
+    assert_eq!(run.status, 1);
+}
+
+#[test]
+fn bullets_go_to_the_nearest_wall() {
+    let run = run(
+        "bullets",
+        r#"
+callmeonmycellphone fail() {
+    shoot 3
+    call coitusinterruptus("wrong after shoot",)
+}
+meth n ∑ 0
+test {
+    test {
+        call fail()
+    } wall e {
+        n ∑ e
+    }
+    shoot 4
+} wall e {
+    n ∑ n ⨋ e
+}
+test {
+    call coitusinterruptus("nothing shot",)
+} wall {
+    call coitusinterruptus("wrong wall",)
+}
+spez n
+"#,
+    );
+    assert_eq!(run.stdout, "nothing shot\n");
+    assert_eq!(run.status, 7);
+}
+
+#[test]
+fn uncaught_bullets_end_the_program() {
+    let run = run("uncaught_bullet", r#"shoot "bang""#);
+    assert_eq!(run.stderr, "Uncaught bullet: \"bang\"\n");
+    assert_eq!(run.status, 1);
+}
+
+#[test]
+fn walls_in_loops_do_not_grow_the_stack() {
+    let run = run(
+        "loop_walls",
+        r#"
+meth i ∑ 0
+meth caught ∑ 0
+repeatdatshid {
+    is i ⅀ 3000000 { sthu }
+    test { shoot i } wall e { caught ∑ e }
+    i ∑ i ⨋ 1
+}
+spez caught – 2999990
+"#,
+    );
+    assert_eq!(run.status, 9);
+}
+
+#[test]
+fn classes_doc_example() {
+    let run = run(
+        "classes_doc",
+        r#"
+school Counter {
+  bar meth count damn Number ∑ 0
+
+  bar callmeonmycellphone tick() {
+    me.count ∑ me.count ⨋ 1
+  }
+}
+
+meth counter damn Counter ∑ call Counter()
+call counter.tick()
+is counter.count ⅀ 1 {
+  call coitusinterruptus("Ticked once",)
+}
+"#,
+    );
+    assert_eq!(run.stdout, "Ticked once\n");
+    assert_eq!(run.status, 0);
+}
+
+#[test]
+fn objects_are_destroyed_with_their_last_holder() {
+    let run = run(
+        "destructors",
+        r#"
+school Noisy {
+    meth name damn String ∑ ""
+    callmeonmycellphone cooK(name damn String,) {
+        me.name ∑ name
+        call coitusinterruptus("cooK",)
+    }
+    callmeonmycellphone snoRt() {
+        call coitusinterruptus(me.name,)
+    }
+}
+meth a damn Noisy ∑ call Noisy("first",)
+meth b damn Noisy ∑ a
+a ∑ call Noisy("second",)
+call coitusinterruptus("still held",)
+b ∑ a
+call coitusinterruptus("end",)
+"#,
+    );
+    assert_eq!(run.stdout, "cooK\ncooK\nstill held\nfirst\nend\nsecond\n");
+}
+
+#[test]
+fn indexed_containers_that_nothing_holds_are_destroyed() {
+    let run = run(
+        "index_temporary",
+        r#"
+school Noisy {
+    meth name damn String ∑ ""
+    callmeonmycellphone cooK(name damn String,) {
+        me.name ∑ name
+    }
+    callmeonmycellphone snoRt() {
+        call coitusinterruptus(me.name,)
+    }
+}
+meth kept ∑ [call Noisy("kept",), call Noisy("dropped",)][1]
+call coitusinterruptus("end",)
+"#,
+    );
+    assert_eq!(run.stdout, "dropped\nend\nkept\n");
+}
+
+#[test]
+fn private_members_are_errors_outside_the_class() {
+    let stdout = cook_error(
+        "private_member",
+        r#"
+school Counter {
+    meth count damn Number ∑ 0
+}
+meth counter damn Counter ∑ call Counter()
+meth n ∑ counter.count
+"#,
+    );
+    assert!(
+        stdout.contains("`count` of `Counter` is private"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("help: Mark it with `bar` to use it outside of the class"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn modules_run_before_main() {
+    let run = run_with(
+        "modules",
+        &[
+            (
+                "main.rl",
+                r#"
+weneed "mathy"
+bringme "shapes"
+
+meth s damn Square ∑ call Square(4,)
+call coitusinterruptus("main",)
+spez call double(call s.area(),)
+"#,
+            ),
+            (
+                "mathy.rl",
+                r#"
+bar callmeonmycellphone double damn Number(x damn Number,) {
+    spez call twice(x,)
+}
+
+callmeonmycellphone twice damn Number(x damn Number,) {
+    spez x ⨋ x
+}
+
+call coitusinterruptus("mathy init",)
+"#,
+            ),
+            (
+                "geo/square.rl",
+                r#"subreddit r/shapes
+weneed "mathy"
+
+bar school Square {
+    meth side damn Number ∑ 0
+    callmeonmycellphone cooK(side damn Number,) {
+        me.side ∑ side
+    }
+    bar callmeonmycellphone area damn Number() {
+        spez me.side ⋇ me.side
+    }
+}
+
+callmeonmycellphone twice damn Number(x damn Number,) {
+    spez call double(x,) ⨋ 1000
+}
+call coitusinterruptus("shapes init",)
+"#,
+            ),
+        ],
+        &[],
+    );
+    assert_eq!(run.stdout, "mathy init\nshapes init\nmain\n");
+    assert_eq!(run.status, 32);
+}
+
+#[test]
+fn private_functions_are_not_imported() {
+    let stdout = cook_error_with(
+        "private_import",
+        &[
+            ("main.rl", "weneed \"mathy\"\ncall twice(1,)"),
+            ("mathy.rl", "callmeonmycellphone twice(x damn Number,) {}"),
+        ],
+    );
+    assert!(
+        stdout.contains("Function `twice` not defined"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn modules_can_not_import_each_other() {
+    let stdout = cook_error_with(
+        "import_cycle",
+        &[
+            ("main.rl", "weneed \"a\""),
+            ("a.rl", "weneed \"b\""),
+            ("b.rl", "weneed \"a\""),
+        ],
+    );
+    assert!(stdout.contains("Modules import each other"), "{}", stdout);
+    assert!(stdout.contains("note: a -> b -> a"), "{}", stdout);
+}
+
+#[test]
+fn subreddit_must_be_at_the_top_of_a_file() {
+    let stdout = cook_error_with(
+        "misplaced_subreddit",
+        &[
+            ("main.rl", "weneed \"late\""),
+            ("late.rl", "meth x ∑ 1\nsubreddit r/late"),
+        ],
+    );
+    assert!(
+        stdout.contains("`subreddit` can only be at the top of a file"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn weneed_can_not_be_used_in_a_block() {
+    let stdout = cook_error("nested_import", "is 1 {\n    weneed \"mathy\"\n}");
+    assert!(
+        stdout.contains("`weneed` can not be used inside of a block"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn arrays_start_at_1_and_grow() {
+    let run = run(
+        "arrays",
+        r#"
+meth numbers damn Number[] ∑ [1, 2, 3]
+numbers[4] ∑ numbers[1] ⨋ numbers[3]
+meth grid ∑ [[1, 2], [3, numbers]]
+numbers[1] ∑ 10
+is [] { call coitusinterruptus("wrong empty array",) }
+is [wat] { call coitusinterruptus("array with items",) }
+spez grid[2][2][4] ⨋ grid[2][2][1]
+"#,
+    );
+    assert_eq!(run.stdout, "array with items\n");
+    assert_eq!(run.status, 14);
+}
+
+#[test]
+fn bad_indexes_shoot() {
+    let run = run(
+        "bad_index",
+        r#"
+meth a ∑ [1, 2, 3]
+meth n ∑ 0
+test { meth x ∑ a[0 ⎲ 0] } wall e { n ∑ n ⨋ 1 }
+test { meth x ∑ a[1 ⎲ 0] } wall e { n ∑ n ⨋ 2 }
+test { meth x ∑ a[100000000000000000000000] } wall e { n ∑ n ⨋ 4 }
+test { meth x ∑ a[1.5] } wall e { n ∑ n ⨋ 8 }
+test { meth x ∑ a[0] } wall e { n ∑ n ⨋ 16 }
+spez n ⨋ a[3]
+"#,
+    );
+    assert_eq!(run.status, 34);
+}
+
+#[test]
+fn out_of_bounds_bullets_name_the_index() {
+    let run = run(
+        "out_of_bounds",
+        r#"
+meth a ∑ [1]
+meth x ∑ a[2]
+"#,
+    );
+    assert_eq!(
+        run.stderr,
+        "Uncaught bullet: \"Index 2 is out of bounds for an array of length 1\"\n"
+    );
+    assert_eq!(run.status, 1);
+}
+
+#[test]
+fn indexing_wat_is_an_error() {
+    let stdout = cook_error("wat_index", "meth x ∑ wat[1]");
+    assert!(stdout.contains("`wat` can not be indexed"), "{}", stdout);
+}
+
+#[test]
+fn maps_can_be_read_written_and_iterated() {
+    let run = run(
+        "maps",
+        r#"
+meth ages damn Map ∑ {"ann": 31, "bob": 40}
+ages["cat"] ∑ 7
+ages["ann"] ∑ ages["ann"] ⨋ 1
+is ages["nobody"] ⅀ wat { call coitusinterruptus("missing is wat",) }
+is {} { call coitusinterruptus("wrong empty map",) }
+meth total ∑ 0
+repeatdatshid name ∈ ages {
+    call coitusinterruptus(name,)
+    total ∑ total ⨋ ages[name]
+}
+spez total
+"#,
+    );
+    assert_eq!(run.stdout, "missing is wat\nann\nbob\ncat\n");
+    assert_eq!(run.status, 79);
+}
+
+#[test]
+fn loops_over_arrays_can_stop_early() {
+    let run = run(
+        "array_loop",
+        r#"
+meth sum ∑ 0
+repeatdatshid x ∈ [1, 2, 3, 4] {
+    is x ⅀ 4 { sthu }
+    sum ∑ sum ⨋ x
+}
+spez sum
+"#,
+    );
+    assert_eq!(run.status, 6);
+}
+
+#[test]
+fn strings_join_and_compare() {
+    let run = run(
+        "strings",
+        r#"
+meth name ∑ "world"
+meth greeting damn String ∑ "Hello, " ⨋ name
+call coitusinterruptus(greeting,)
+is "" { call coitusinterruptus("wrong empty string",) }
+is "abc" < "abd" { call coitusinterruptus("abc < abd",) }
+is greeting ⅀ "Hello, world" { call coitusinterruptus("equal",) }
+meth i ∑ 0
+meth s ∑ ""
+repeatdatshid {
+    is i ⅀ 100000 { sthu }
+    s ∑ "x" ⨋ name
+    i ∑ i ⨋ 1
+}
+call coitusinterruptus(s,)
+"#,
+    );
+    assert_eq!(run.stdout, "Hello, world\nabc < abd\nequal\nxworld\n");
+}
+
+#[test]
+fn arguments_can_be_expressions() {
+    let run = run(
+        "expression_arguments",
+        r#"
+callmeonmycellphone add damn Number(a damn Number, b damn Number,) {
+    spez a ⨋ b
+}
+spez call add(1 ⨋ 2, call add(2, 2,) ⋇ 2,)
+"#,
+    );
+    assert_eq!(run.status, 11);
+}
+
+#[test]
+fn calls_must_pass_every_argument() {
+    let stdout = cook_error(
+        "arity",
+        r#"
+callmeonmycellphone add(a, b,) {}
+call add(1,)
+"#,
+    );
+    assert!(
+        stdout.contains("`add` takes 2 arguments but got 1"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn arguments_must_match_their_type() {
+    let stdout = cook_error(
+        "argument_type",
+        r#"
+callmeonmycellphone twice(x damn Number,) {}
+call twice("two",)
+"#,
+    );
+    assert!(
+        stdout.contains("Argument 1 of `twice` must be a `Number` but got a `String`"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn wat_can_be_held_and_compared() {
+    let run = run(
+        "wat_values",
+        r#"
+school Box {
+    bar meth n damn Number ∑ 5
+}
+callmeonmycellphone check(x,) {
+    is x ⅀ wat { call coitusinterruptus("got wat",) }
+}
+meth b damn Box ∑ wat
+meth f damn Foolean ∑ wat
+meth x ∑ wat
+call check(x,)
+is b { call coitusinterruptus("wrong wat is truthy",) }
+is f ⅀ Dunno { call coitusinterruptus("Dunno",) }
+is wat ≠ 0 { call coitusinterruptus("wat is not 0",) }
+b ∑ call Box()
+spez b.n
+"#,
+    );
+    assert_eq!(run.stdout, "got wat\nDunno\nwat is not 0\n");
+    assert_eq!(run.status, 5);
+}
+
+#[test]
+fn members_of_wat_shoot() {
+    let run = run(
+        "wat_member",
+        r#"
+school Box {
+    bar meth n damn Number ∑ 5
+}
+meth b damn Box ∑ wat
+meth n ∑ b.n
+"#,
+    );
+    assert_eq!(run.stderr, "Uncaught bullet: \"Can not use `n` on wat\"\n");
+    assert_eq!(run.status, 1);
+}
+
+#[test]
+fn sthu_stops_the_innermost_loop() {
+    let run = run(
+        "nested_loops",
+        r#"
+meth outer ∑ 0
+meth inner ∑ 0
+repeatdatshid {
+    is outer ⅀ 3 { sthu }
+    outer ∑ outer ⨋ 1
+    repeatdatshid {
+        inner ∑ inner ⨋ 1
+        sthu
+    }
+}
+spez outer ⋇ 10 ⨋ inner
+"#,
+    );
+    assert_eq!(run.status, 33);
+}
+
+#[test]
+fn sthu_outside_of_a_loop_is_an_error() {
+    let stdout = cook_error(
+        "top_level_sthu",
+        r#"
+callmeonmycellphone stop() {
+    sthu
+}
+"#,
+    );
+    assert!(
+        stdout.contains("`sthu` can only be used inside of a loop"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn errors_point_at_the_code() {
+    let stdout = cook_error_with(
+        "error_span",
+        &[
+            ("main.rl", "weneed \"mathy\""),
+            ("mathy.rl", "meth x ∑ 1\n\ncall nope(x,)\n"),
+        ],
+    );
+    assert!(stdout.contains(" --> src/mathy.rl:3:6\n"), "{}", stdout);
+    assert!(stdout.contains("3 | call nope(x,)\n"), "{}", stdout);
+}
+
+#[test]
+fn every_problem_is_shown_before_stopping() {
+    let stdout = cook_error_with(
+        "problems",
+        &[
+            ("main.rl", "weneed \"mathy\"\nmeth x ∑ y"),
+            ("mathy.rl", "call nope()"),
+        ],
+    );
+    assert!(stdout.contains("Function `nope` not defined"), "{}", stdout);
+    assert!(stdout.contains("Variable `y` not defined"), "{}", stdout);
+    assert!(
+        stdout.contains("Can not compile because of 2 errors"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn annotations_are_checked_before_compiling() {
+    let stdout = cook_error("annotations", "meth x ∑ 1\nmeth n damn String ∑ x");
+    assert!(stdout.contains(" --> src/main.rl:2:22\n"), "{}", stdout);
+    assert!(
+        stdout.contains("Variable `n` is declared as a `String` but got a `Number`"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn typed_values_are_checked_in_debug_builds() {
+    let run = run(
+        "typed_debug",
+        r#"
+meth things ∑ {"name": "bob"}
+meth n damn Number ∑ things["name"]
+"#,
+    );
+    assert_eq!(
+        run.stderr,
+        "Uncaught bullet: \"Variable `n` is declared as a Number but got a String\"\n"
+    );
+    assert_eq!(run.status, 1);
+}
+
+#[test]
+fn typed_arrays_check_their_items() {
+    let run = run(
+        "typed_array",
+        r#"
+meth things ∑ [1, "two"]
+meth nums damn Number[] ∑ things
+"#,
+    );
+    assert_eq!(
+        run.stderr,
+        "Uncaught bullet: \"Variable `nums` is declared as a Number[] but item 2 is a String\"\n"
+    );
+    assert_eq!(run.status, 1);
+}
+
+#[test]
+fn release_builds_do_not_check_types() {
+    let source = r#"
+meth a ∑ [1, "two"]
+meth nums damn Number[] ∑ a
+spez 5
+"#;
+    let run = run_with("typed_release", &[("main.rl", source)], &["--release"]);
+    assert_eq!(run.status, 5);
+}
+
+#[test]
+fn long_identifiers_are_an_anti_java_exception() {
+    let stdout = cook_error("anti_java", "meth abstractSingletonProxyFactoryBean ∑ 1");
+    assert!(
+        stdout.contains(
+            "AntiJavaException: `abstractSingletonProxyFactoryBean` is 33 characters long"
+        ),
+        "{}",
+        stdout
+    );
+
+    let stdout = cook_error_with(
+        "anti_java_module",
+        &[
+            ("main.rl", "weneed \"abstractSingletonProxyFactory\""),
+            ("abstractSingletonProxyFactory.rl", "meth x ∑ 1"),
+        ],
+    );
+    assert!(stdout.contains("AntiJavaException"), "{}", stdout);
+}
+
+#[test]
+fn parsed_files_are_checked_before_stopping() {
+    let stdout = cook_error(
+        "parse_and_check",
+        "meth abstractSingletonProxyFactoryBean ∑ 1\nmeth x ∑ y",
+    );
+    assert!(stdout.contains("AntiJavaException"), "{}", stdout);
+    assert!(stdout.contains("Variable `y` not defined"), "{}", stdout);
+    assert!(
+        stdout.contains("Can not compile because of 2 errors"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn programs_can_define_main() {
+    let run = run(
+        "user_main",
+        r#"
+callmeonmycellphone main() {
+    spez 3
+}
+callmeonmycellphone free(x,) {
+    call coitusinterruptus(x,)
+}
+call free("freed",)
+spez call main()
+"#,
+    );
+    assert_eq!(run.stdout, "freed\n");
+    assert_eq!(run.status, 3);
+}
+
+#[test]
+fn warnings_do_not_stop_the_build() {
+    let dir = project(
+        "warning",
+        &[(
+            "main.rl",
+            r#"
+callmeonmycellphone one() {
+    spez 1
+    call coitusinterruptus("never",)
+}
+spez call one()
+"#,
+        )],
+    );
+    let output = cook(&dir, &[]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("This code is never run"), "{}", stdout);
+}
+
+#[test]
+fn strings_are_ordered_by_their_bytes() {
+    let run = run(
+        "string_order",
+        r#"
+callmeonmycellphone lt(a, b,) {
+    spez a < b
+}
+meth n ∑ 0
+is call lt("banana", "apple",) { n ∑ n ⨋ 1 }
+is call lt("b", "a",) { n ∑ n ⨋ 2 }
+is call lt("a", "b",) { n ∑ n ⨋ 4 }
+is call lt(1, 2,) { n ∑ n ⨋ 8 }
+is call lt("ab", "abc",) { n ∑ n ⨋ 16 }
+test { call lt(1, "a",) } wall e { n ∑ n ⨋ 32 }
+spez n
+"#,
+    );
+    assert_eq!(run.status, 60);
+}
+
+#[test]
+fn math_on_a_string_shoots() {
+    let run = run(
+        "string_math",
+        r#"
+callmeonmycellphone sub(a,) {
+    spez a – 1
+}
+callmeonmycellphone neg(a,) {
+    spez –a
+}
+meth n ∑ call sub(5,) ⨋ call neg(3,)
+test { call sub("hi",) } wall e { n ∑ n ⨋ 10 }
+test { call neg("hi",) } wall e { n ∑ n ⨋ 20 }
+spez n
+"#,
+    );
+    assert_eq!(run.status, 31);
+}
+
+#[test]
+fn typing_doc_example() {
+    let stdout = cook_error(
+        "typing_doc",
+        r#"
+meth nums damn Number[] ∑ [1, 2]
+nums[1] ∑ "three" # Error
+"#,
+    );
+    assert!(
+        stdout.contains("Can not put a `String` in a `Number[]`"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn files_that_are_not_imported_do_not_have_to_compile() {
+    let run = run_with(
+        "unimported",
+        &[
+            ("main.rl", "weneed \"mathy\"\nspez call double(2,)"),
+            (
+                "mathy.rl",
+                "subreddit r/mathy\n\nbar callmeonmycellphone double(x,) {\n    spez x ⋇ 2\n}",
+            ),
+            // Never imported, only its `subreddit` line is read
+            ("broken.rl", "subreddit r/broken\nmeth x ∑ ("),
+        ],
+        &[],
+    );
+    assert_eq!(run.status, 4);
+}